@@ -0,0 +1,54 @@
+use std::{fs, path::Path, process::Command};
+
+use color_print::{cformat, cprintln};
+
+use crate::QuikcFlags;
+use crate::flags;
+
+use crate::build::{Build, Target};
+use crate::compiler;
+
+/// Returns the command that archives `object_files` into the static library of `target`.
+pub fn archive_command(build_config : &Build, target : &Target, object_files : &[String]) -> Command
+{
+    let mut cmd = build_config.execute_archiver_with_build_info();
+    cmd.arg(target.output_file())
+       .args(object_files.iter());
+    cmd
+}
+
+/// Runs `cmd` to archive the object files used by `target` into a static
+/// library. If nothing went wrong, this function will return true. Otherwise,
+/// it will terminate the program. The bool return value is only used for
+/// testing purposes.
+pub fn archive_files(target : &Target, mut cmd : Command) -> bool
+{
+    let output_file = target.output_file();
+
+    if flags()&QuikcFlags::HIDE_OUTPUT == QuikcFlags::NONE {
+        cprintln!("<green><bold>Archiving static library</bold> '{}'...</green>", output_file);
+    }
+
+    // 'ar' only replaces members, so objects of deleted source files would
+    // stay in the library if the old archive is kept around
+    if Path::new(&output_file).exists() {
+        fs::remove_file(&output_file).expect("Failed to remove old static library");
+    }
+
+    compiler::print_command(&cmd);
+    let cmd = cmd.output().expect("Failed to execute archiver");
+
+    if !cmd.status.success() {
+        let err_output = String::from_utf8_lossy(&cmd.stderr);
+        eprintln!("{}\n{}", 
+                    cformat!("<bold><red>error</red>:</bold> Failed to archive static library '{}'", output_file), 
+                    err_output);
+
+        #[cfg(not(test))]
+            std::process::exit(1);
+        #[cfg(test)]
+            return false;
+    }
+
+    true
+}
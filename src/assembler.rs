@@ -5,7 +5,7 @@ use std::{path::{PathBuf, Path}, fs};
 use color_print::{cprintln, cformat};
 use rayon::prelude::{IntoParallelIterator, ParallelIterator};
 
-use crate::{SOURCE_DIRECTORY, buildtable::{BUILD_TABLE_ASM_DIRECTORY}, build::Build, flags, QuikcFlags, compiler::{self, INCLUDE_PATH_FLAG}};
+use crate::{build::Build, flags, QuikcFlags, compiler, walker};
 
 
 /// Returns true if the assembler should use the default configuration.
@@ -16,7 +16,7 @@ pub fn use_default_assembler_configuration(asm_args : &Option<Vec<String>>) -> b
     asm_args.is_none() || asm_args.as_ref().unwrap().is_empty()
 }
 
-/// Compiles the source files to their assembly equivalants in /buildinfo/asm
+/// Compiles the source files to their assembly equivalants in the profile's 'asm' directory
 /// with the given build configuration. Returns true if no errors occurred.
 fn compile_to_asm_files(source_files : &Vec<&String>,
                         build : &Build) -> bool
@@ -29,11 +29,13 @@ fn compile_to_asm_files(source_files : &Vec<&String>,
             cprintln!("<green><bold>Assembling </bold>'{}'...</green>", file);
         }
 
-        let out_file = compiler::to_output_file(&PathBuf::from(&file), BUILD_TABLE_ASM_DIRECTORY, "s");
-        let output = build.execute_assembler_with_build_info(file)
-                                        .args([INCLUDE_PATH_FLAG, file, "-S", "-o", &out_file])
-                                        .output()
-                                        .expect("Failed to execute assembler");
+        let out_file = compiler::to_output_file(&PathBuf::from(&file), &build.dirs.asm, "s");
+        compiler::create_output_directory(&out_file);
+        let mut cmd = build.execute_assembler_with_build_info(file);
+        cmd.args(build.include_flags())
+           .args([file, "-S", "-o", &out_file]);
+        compiler::print_command(&cmd);
+        let output = cmd.output().expect("Failed to execute assembler");
 
         if !output.status.success() {
             let s = String::from_utf8_lossy(&output.stderr);
@@ -67,22 +69,16 @@ fn compile_to_asm_files(source_files : &Vec<&String>,
 pub fn assemble_files(files : &Vec<&String>, 
                       build : &Build) -> bool
 {
-    if !Path::new(BUILD_TABLE_ASM_DIRECTORY).exists() {
-        fs::create_dir(BUILD_TABLE_ASM_DIRECTORY).expect("Failed to create directory");
+    if !Path::new(&build.dirs.asm).exists() {
+        fs::create_dir_all(&build.dirs.asm).expect("Failed to create directory");
     }
 
     // If there are no files specified, then just assume the user wants to
     // assemble all of the source files
     if files.is_empty() {
-        let paths = fs::read_dir(SOURCE_DIRECTORY).expect("Failed to read from directory");
-        let mut files = Vec::new();
-        for file in paths.flatten() {
-            let path = file.path();
-            let file_str = path.to_str().unwrap();
-            if compiler::is_c_source_file(file_str) || compiler::is_cpp_source_file(file_str) {
-                files.push(file_str.to_string());
-            }
-        }
+        let files = walker::find_source_files(build).iter()
+                                                      .map(|file| file.to_str().unwrap().to_string())
+                                                      .collect::<Vec<String>>();
         return compile_to_asm_files(&files.iter().collect(), build);
     }
     // compile the files given by the user
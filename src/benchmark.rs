@@ -3,7 +3,7 @@ use std::{time::Instant, env, path::{Path, PathBuf}, fs::{File, self}, io::Write
 use color_print::cprintln;
 use once_cell::sync::Lazy;
 
-use crate::{build::Build, walker, SOURCE_DIRECTORY, buildtable::{BUILD_TABLE_DIRECTORY, BuildTable, BUILD_TABLE_OBJECT_FILE_DIRECTORY, BUILD_TABLE_FILE}, test::{Tools, modify_file_time, self}};
+use crate::{build::Build, walker, buildtable::{BuildTable, command_fingerprint}, compiler::{object_file_command, to_output_file, create_output_directory}, test::{Tools, modify_file_time, self}};
 
 const SAMPLES : usize = 10000;
 const BENCHMARK_LOG_FILE_PATH : &str = "../benchmark.log";
@@ -66,12 +66,13 @@ fn reset() -> Result<(), Box<dyn std::error::Error>>
 {
     // Do not delete everything as the dependencies directory can't be regenerated unless
     // running the compiler (not recommended)
-    if Path::new(BUILD_TABLE_DIRECTORY).exists() {
-        if Path::new(BUILD_TABLE_OBJECT_FILE_DIRECTORY).exists() {
-            std::fs::remove_dir_all(BUILD_TABLE_OBJECT_FILE_DIRECTORY)?;
+    let dirs = Build::new().dirs;
+    if Path::new(&dirs.root).exists() {
+        if Path::new(&dirs.obj).exists() {
+            std::fs::remove_dir_all(&dirs.obj)?;
         }
-        if Path::new(BUILD_TABLE_FILE).exists() {
-            std::fs::remove_file(BUILD_TABLE_FILE)?;
+        if Path::new(&dirs.table).exists() {
+            std::fs::remove_file(&dirs.table)?;
         }
     }
     Ok(())
@@ -184,12 +185,12 @@ fn quikc_benchmark() -> Result<(), Box<dyn std::error::Error>>
     reset()?;
 
     benchmark_fn("time to initialize build configuration", &mut || {Build::new();});
-    benchmark_fn("time to initialize build table", &mut || {BuildTable::new(&mut HashMap::new());});
+    benchmark_fn("time to initialize build table", &mut || {BuildTable::new(&Build::new(), &mut HashMap::new());});
 
     // Benchmark first time retrieving source file speed
     {
         let mut tools = Tools::new();
-        benchmark_fn("time to retrieve source files on first compilation",&mut || {walker::retrieve_source_files(SOURCE_DIRECTORY, 
+        benchmark_fn("time to retrieve source files on first compilation",&mut || {walker::retrieve_source_files(&tools.build_config,
                                 &mut tools.build_table,
                                 &tools.old_table);});
     }
@@ -199,17 +200,21 @@ fn quikc_benchmark() -> Result<(), Box<dyn std::error::Error>>
         modify_file_time("./include/mcvk/device.hpp")?;
         // Since we didn't actually compile any files, just make a fake object file so that the program
         // will actually behave as intended
-        File::create(format!("{}/{}", BUILD_TABLE_OBJECT_FILE_DIRECTORY, "device.o"))?;
+        let object_file = to_output_file(Path::new("./src/device.cpp"), &Build::new().dirs.obj, "o");
+        create_output_directory(&object_file);
+        File::create(object_file)?;
         let mut tools = Tools::new();
-        benchmark_fn("time to retrieve source files on header file change",&mut || {walker::retrieve_source_files(SOURCE_DIRECTORY, 
+        benchmark_fn("time to retrieve source files on header file change",&mut || {walker::retrieve_source_files(&tools.build_config,
                                 &mut tools.build_table,
                                 &tools.old_table);});
     }
 
     {
         let mut tools = Tools::new();
+        let command = command_fingerprint(&object_file_command(&tools.build_config, "./src/device.cpp"));
         benchmark_fn("time to check if a file needs to be recompiled", &mut || {
             tools.build_table.needs_to_be_recompiled(&PathBuf::from("./src/device.cpp"), 
+                                                     command,
                                                      &tools.old_table);
         });
     }
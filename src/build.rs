@@ -1,34 +1,79 @@
-use std::{fs::{self}, process::Command, path::Path};
+use std::{fs::{self}, process::Command, path::{Path, Component}, collections::{HashSet, HashMap}};
 use color_print::{cprintln, cformat};
+use globset::GlobSet;
 #[cfg(test)]
     use serde_derive::Serialize;
 use serde_derive::{Deserialize};
 use crate::example;
 use crate::logger;
 
-use crate::{defaultbuild::{GCC_COMPILER_NONEXCLUSIVE_WARNINGS, GCC_COMPILER_C_EXCLUSIVE_WARNINGS, GCC_COMPILER_CPP_DIALECT_OPTIONS, GCC_COMPILER_CPP_EXCLUSIVE_WARNINGS, GCC_STATIC_ANALYSIS_OPTIONS, GCC_AND_CLANG_DIALECT_OPTIONS, CLANG_COMPILER_NONEXCLUSIVE_WARNINGS, CLANG_COMPILER_CPP_WARNINGS, GCC_AND_CLANG_OPTIMIZATION_OPTIONS, GCC_AND_CLANG_ENHANCED_OPTIMIZATION_OPTIONS, GCC_AND_CLANG_LINKER_OPTIONS, GCC_AND_CLANG_CPP_DIALECT_OPTIONS}, compiler::{self, use_default_compiler_configuration, select_default_compiler, INCLUDE_PATH}, buildtable::{BUILD_TABLE_OBJECT_FILE_DIRECTORY, BUILD_TABLE_DIRECTORY, BUILD_TABLE_FILE}, linker, SOURCE_DIRECTORY, QuikcFlags, flags, logger::{error}, assembler::use_default_assembler_configuration};
+use crate::{defaultbuild::{GCC_COMPILER_NONEXCLUSIVE_WARNINGS, GCC_COMPILER_C_EXCLUSIVE_WARNINGS, GCC_COMPILER_CPP_DIALECT_OPTIONS, GCC_COMPILER_CPP_EXCLUSIVE_WARNINGS, GCC_STATIC_ANALYSIS_OPTIONS, GCC_AND_CLANG_DIALECT_OPTIONS, CLANG_COMPILER_NONEXCLUSIVE_WARNINGS, CLANG_COMPILER_CPP_WARNINGS, GCC_AND_CLANG_LINKER_OPTIONS, GCC_AND_CLANG_CPP_DIALECT_OPTIONS, GCC_AND_CLANG_RELAXED_WARNINGS, GCC_STRICT_WARNINGS, GCC_STRICT_C_WARNINGS, CLANG_STRICT_WARNINGS, AR_OPTIONS}, compiler::{self, use_default_compiler_configuration, select_default_compiler, INCLUDE_PATH}, buildtable::{BUILD_TABLE_DIRECTORY, BuildDirectories}, linker, SOURCE_DIRECTORY, QuikcFlags, flags, selected_profile, logger::{error}, assembler::use_default_assembler_configuration, toolchain::{self, CompilerFamily, CompilerInfo}, walker};
 
 pub const BUILD_CONFIG_FILE : &str = "./Build.toml";
-pub const BUILD_CONFIG_CACHE_FILE : &str = "./buildinfo/.buildcache";
 pub const DEFAULT_C_STANDARD : &str = "-std=c17";
 pub const DEFAULT_CPP_STANDARD : &str = "-std=c++20";
+pub const DEFAULT_ARCHIVER : &str = "ar";
+/// Defined for every source file if a target is a shared library. Symbols in the
+/// export list of a shared library with hidden visibility have to be declared or
+/// defined with it.
+pub const EXPORT_MACRO : &str = "QUIKC_EXPORT";
+pub const DEV_PROFILE : &str = "dev";
+pub const RELEASE_PROFILE : &str = "release";
+
+/// The kind of file a target produces.
+#[cfg_attr(test, derive(Serialize))]
+#[derive(Deserialize, PartialEq, Eq, Default, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum TargetKind
+{
+    #[default]
+    Executable,
+    Static,
+    Shared
+}
+
+/// The visibility the symbols of a shared library are compiled with.
+#[cfg_attr(test, derive(Serialize))]
+#[derive(Deserialize, PartialEq, Eq, Default, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Visibility
+{
+    #[default]
+    Default,
+    // '-fvisibility=hidden', only the symbols declared with 'EXPORT_MACRO' are visible
+    Hidden
+}
 
 #[cfg_attr(test, derive(Serialize))]
 #[derive(Deserialize, PartialEq, Default)]
 pub struct Package
 {
     pub name : String,
-    pub debug_build : bool
+    pub debug_build : bool,
+    pub kind : Option<TargetKind>,
+    pub version : Option<String>,
+    pub exports : Option<Vec<String>>,
+    pub version_script : Option<String>,
+    pub visibility : Option<Visibility>
 }
 
 #[cfg_attr(test, derive(Serialize))]
 #[derive(Deserialize, PartialEq, Default)]
 pub struct Compiler
 {
+    // used for both languages unless 'c_compiler' or 'cpp_compiler' is given,
+    // in which case its C or C++ counterpart is used (e.g. 'gcc' and 'g++')
+    #[serde(default)]
     pub compiler : String,
+    pub c_compiler : Option<String>,
+    pub cpp_compiler : Option<String>,
     pub args : Option<Vec<String>>,
+    pub c_args : Option<Vec<String>>,
+    pub cpp_args : Option<Vec<String>>,
     pub cstd : Option<String>,
     pub cppstd : Option<String>,
+    // macros defined with '-D' for every source file, even if 'args' replace the defaults
+    pub defines : Option<Vec<String>>,
     pub append_args : Option<bool> 
 }
 
@@ -45,12 +90,79 @@ pub struct Linker
 #[derive(Deserialize, PartialEq, Default, Clone, Copy)]
 pub struct Misc
 {
-    pub optimization_level : Option<u32>,
+    pub optimization_level : Option<OptimizationLevel>,
+    pub lto : Option<LtoMode>,
     pub static_analysis_enabled : Option<bool>,
+    // write 'compile_commands.json' on every build
+    pub compile_commands : Option<bool>,
     #[cfg(feature = "quikc-nightly")]
     pub toggle_iwyu : Option<bool> 
 }
 
+/// How many warnings the default configuration enables.
+#[cfg_attr(test, derive(Serialize))]
+#[derive(Deserialize, PartialEq, Eq, Default, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum WarningLevel
+{
+    // the default warnings and some more that are noisy on most existing code
+    Strict,
+    #[default]
+    Default,
+    // only '-Wall' and '-Wextra'
+    Relaxed,
+    // no warning flags are added
+    None
+}
+
+/// The '[warnings]' section. The level and whether warnings are treated as errors
+/// only apply to the default configuration, while the warnings that are allowed
+/// ('-Wno-<warning>') or denied ('-Werror=<warning>') are always passed to gcc and
+/// clang. Warnings are given without the '-W' prefix, e.g. "shadow".
+#[cfg_attr(test, derive(Serialize))]
+#[derive(Deserialize, PartialEq, Default, Clone)]
+pub struct Warnings
+{
+    pub level : Option<WarningLevel>,
+    pub as_errors : Option<bool>,
+    pub allow : Option<Vec<String>>,
+    pub deny : Option<Vec<String>>
+}
+
+impl Warnings
+{
+    #[inline]
+    pub fn level(&self) -> WarningLevel
+    {
+        self.level.unwrap_or_default()
+    }
+
+    /// Warnings are treated as errors by default, unless the warning level is
+    /// "relaxed" or "none", since those are meant for code that has warnings.
+    pub fn as_errors(&self) -> bool
+    {
+        self.as_errors.unwrap_or(matches!(self.level(), WarningLevel::Strict | WarningLevel::Default))
+    }
+
+    /// Returns the flags that allow and deny the configured warnings. Third
+    /// party files do not have to pass the project's warnings, so nothing is
+    /// denied in them.
+    pub fn policy_args(&self, third_party : bool) -> impl Iterator<Item = String> + '_
+    {
+        let allowed = self.allow.iter().flatten().map(|warning| format!("-Wno-{}", warning_name(warning)));
+        let denied = self.deny.iter().flatten()
+                              .filter(move |_| !third_party)
+                              .map(|warning| format!("-Werror={}", warning_name(warning)));
+        allowed.chain(denied)
+    }
+}
+
+/// Returns the name of `warning`, which may be given as "shadow" or "-Wshadow".
+fn warning_name(warning : &str) -> &str
+{
+    warning.strip_prefix("-W").unwrap_or(warning)
+}
+
 #[cfg_attr(test, derive(Serialize))]
 #[derive(Deserialize, PartialEq, Default)]
 pub struct Assembler
@@ -59,6 +171,416 @@ pub struct Assembler
     pub args : Option<Vec<String>>
 }
 
+#[cfg_attr(test, derive(Serialize))]
+#[derive(Deserialize, PartialEq, Default)]
+pub struct Archiver
+{
+    // 'ar' is used if the section only sets the arguments
+    #[serde(default)]
+    pub archiver : String,
+    pub args : Option<Vec<String>>
+}
+
+/// How much the compiler optimizes the code. In 'Build.toml', this is either
+/// a level from 0 to 3 or one of "s", "z", "g" and "fast". Levels 1 and 3 have
+/// to be strings, since they meant something else as integers.
+#[cfg_attr(test, derive(Serialize), serde(into = "String"))]
+#[derive(Deserialize, PartialEq, Eq, Clone, Copy, Debug)]
+#[serde(try_from = "toml::Value")]
+pub enum OptimizationLevel
+{
+    None,
+    Basic,
+    Default,
+    Aggressive,
+    Size,
+    MinSize,
+    Debug,
+    Fast
+}
+
+impl OptimizationLevel
+{
+    /// Returns the flag that selects the optimization level.
+    pub fn flag(self) -> &'static str
+    {
+        match self {
+            OptimizationLevel::None => "-O0",
+            OptimizationLevel::Basic => "-O1",
+            OptimizationLevel::Default => "-O2",
+            OptimizationLevel::Aggressive => "-O3",
+            OptimizationLevel::Size => "-Os",
+            OptimizationLevel::MinSize => "-Oz",
+            OptimizationLevel::Debug => "-Og",
+            OptimizationLevel::Fast => "-Ofast"
+        }
+    }
+
+    /// Returns the spellings that replace the integer `level`, if it meant something
+    /// else before the levels were mapped to '-O<level>'. 1 used to not optimize
+    /// at all and 3 used to select '-Ofast' for the machine quikc runs on, so
+    /// these have to be written as strings now.
+    fn legacy_replacement(level : i64) -> Option<&'static str>
+    {
+        match level {
+            1 => Some("it used to disable optimizations, write 0 for that or \"1\" for '-O1'"),
+            3 => Some("it used to select '-Ofast -march=native -mtune=native', write \"fast\" (and add '-march=native' \
+                       and '-mtune=native' to the compiler's 'args') for that or \"3\" for '-O3'"),
+            _ => None
+        }
+    }
+}
+
+impl TryFrom<toml::Value> for OptimizationLevel
+{
+    type Error = String;
+
+    fn try_from(value : toml::Value) -> Result<Self, Self::Error>
+    {
+        let level = match &value {
+            toml::Value::Integer(level) => {
+                if let Some(replacement) = OptimizationLevel::legacy_replacement(*level) {
+                    return Err(format!("optimization level {level} is no longer supported, {replacement}"));
+                }
+                level.to_string()
+            },
+            toml::Value::String(level) => level.to_owned(),
+            _ => String::new()
+        };
+        match level.as_str() {
+            "0" => Ok(OptimizationLevel::None),
+            "1" => Ok(OptimizationLevel::Basic),
+            "2" => Ok(OptimizationLevel::Default),
+            "3" => Ok(OptimizationLevel::Aggressive),
+            "s" => Ok(OptimizationLevel::Size),
+            "z" => Ok(OptimizationLevel::MinSize),
+            "g" => Ok(OptimizationLevel::Debug),
+            "fast" => Ok(OptimizationLevel::Fast),
+            _ => Err(format!("invalid optimization level {value}, expected 0, \"1\", 2, \"3\", \"s\", \"z\", \"g\" or \"fast\""))
+        }
+    }
+}
+
+impl From<OptimizationLevel> for String
+{
+    fn from(level : OptimizationLevel) -> Self
+    {
+        level.flag().trim_start_matches("-O").to_string()
+    }
+}
+
+/// The kind of link time optimization. In 'Build.toml', this is one of "off",
+/// "full", "thin" (clang only) and "auto", or a boolean ("full" or "off").
+#[cfg_attr(test, derive(Serialize), serde(into = "String"))]
+#[derive(Deserialize, PartialEq, Eq, Clone, Copy, Debug)]
+#[serde(try_from = "toml::Value")]
+pub enum LtoMode
+{
+    Off,
+    Full,
+    Thin,
+    // gcc runs the link time optimization with as many jobs as there are
+    // cores, clang uses thin LTO which is always parallel
+    Auto
+}
+
+impl LtoMode
+{
+    /// Returns the flag that enables the link time optimization with a compiler
+    /// of the given family, it has to be passed to both the compiler and the linker.
+    pub fn flag(self, family : CompilerFamily) -> Option<&'static str>
+    {
+        match self {
+            LtoMode::Off => None,
+            LtoMode::Full => Some("-flto"),
+            LtoMode::Thin => Some("-flto=thin"),
+            LtoMode::Auto if family == CompilerFamily::Clang => Some("-flto=thin"),
+            LtoMode::Auto => Some("-flto=auto")
+        }
+    }
+}
+
+impl TryFrom<toml::Value> for LtoMode
+{
+    type Error = String;
+
+    fn try_from(value : toml::Value) -> Result<Self, Self::Error>
+    {
+        match &value {
+            toml::Value::Boolean(true) => Ok(LtoMode::Full),
+            toml::Value::Boolean(false) => Ok(LtoMode::Off),
+            toml::Value::String(mode) if mode == "off" => Ok(LtoMode::Off),
+            toml::Value::String(mode) if mode == "full" => Ok(LtoMode::Full),
+            toml::Value::String(mode) if mode == "thin" => Ok(LtoMode::Thin),
+            toml::Value::String(mode) if mode == "auto" => Ok(LtoMode::Auto),
+            _ => Err(format!("invalid LTO mode {value}, expected \"off\", \"full\", \"thin\", \"auto\" or a boolean"))
+        }
+    }
+}
+
+impl From<LtoMode> for String
+{
+    fn from(mode : LtoMode) -> Self
+    {
+        match mode {
+            LtoMode::Off => "off",
+            LtoMode::Full => "full",
+            LtoMode::Thin => "thin",
+            LtoMode::Auto => "auto"
+        }.to_string()
+    }
+}
+
+/// The settings of a build profile, declared with a '[profile.<name>]' section.
+/// The 'dev' and 'release' profiles are built in, every other profile must
+/// inherit the settings it does not specify from another one.
+#[cfg_attr(test, derive(Serialize))]
+#[derive(Deserialize, PartialEq, Default, Clone, Debug)]
+pub struct Profile
+{
+    pub inherits : Option<String>,
+    pub debug_info : Option<bool>,
+    pub optimization_level : Option<OptimizationLevel>,
+    pub lto : Option<LtoMode>,
+    pub defines : Option<Vec<String>>,
+    pub include_dirs : Option<Vec<String>>
+}
+
+/// The profile the project is built with, after its settings were resolved.
+#[derive(Deserialize, PartialEq, Default, Clone, Debug)]
+pub struct ActiveProfile
+{
+    pub name : String,
+    pub debug_info : bool,
+    // if not specified, '[misc] optimization_level' and '[misc] lto' are used
+    pub optimization_level : Option<OptimizationLevel>,
+    pub lto : Option<LtoMode>,
+    pub defines : Vec<String>,
+    // searched after the include directories of the '[sources]' section
+    pub include_dirs : Vec<String>
+}
+
+/// The toolchain given by the conventional environment variables. 'CC' and 'CXX'
+/// take precedence over the compilers in 'Build.toml'. 'CPPFLAGS' (both languages),
+/// 'CFLAGS' (C), 'CXXFLAGS' (C++) and 'LDFLAGS' (linker) are split on whitespace and
+/// added after the arguments from 'Build.toml', without replacing the default
+/// configuration.
+#[derive(Deserialize, PartialEq, Eq, Default, Clone, Debug)]
+pub struct Environment
+{
+    pub cc : Option<String>,
+    pub cxx : Option<String>,
+    pub cppflags : Vec<String>,
+    pub cflags : Vec<String>,
+    pub cxxflags : Vec<String>,
+    pub ldflags : Vec<String>
+}
+
+impl Environment
+{
+    /// Reads the toolchain from the environment. Empty variables are ignored.
+    pub fn from_env() -> Environment
+    {
+        let var = |name : &str| std::env::var(name).ok().filter(|value| !value.trim().is_empty());
+        let flags = |name : &str| var(name).map(|value| value.split_whitespace().map(String::from).collect())
+                                           .unwrap_or_default();
+        Environment {
+            cc : var("CC"),
+            cxx : var("CXX"),
+            cppflags : flags("CPPFLAGS"),
+            cflags : flags("CFLAGS"),
+            cxxflags : flags("CXXFLAGS"),
+            ldflags : flags("LDFLAGS")
+        }
+    }
+
+    /// Returns the flags that are passed when compiling a source file of the given language.
+    pub fn compiler_flags(&self, is_c_source_file : bool) -> impl Iterator<Item = &String>
+    {
+        let language_flags = if is_c_source_file {&self.cflags} else {&self.cxxflags};
+        self.cppflags.iter().chain(language_flags)
+    }
+}
+
+/// Glob patterns that select files by their path relative to the project's
+/// root directory. The patterns are compiled once, when 'Build.toml' is read.
+#[cfg_attr(test, derive(Serialize), serde(into = "Vec<String>"))]
+#[derive(Deserialize, Clone, Default, Debug)]
+#[serde(try_from = "Vec<String>")]
+pub struct FileGlobs
+{
+    patterns : Vec<String>,
+    set : GlobSet
+}
+
+impl FileGlobs
+{
+    /// Returns true if `file` matches any of the patterns.
+    pub fn is_match(&self, file : &str) -> bool
+    {
+        self.set.is_match(compiler::without_current_dir(Path::new(file)))
+    }
+}
+
+impl PartialEq for FileGlobs
+{
+    fn eq(&self, other : &Self) -> bool
+    {
+        self.patterns == other.patterns
+    }
+}
+
+impl TryFrom<Vec<String>> for FileGlobs
+{
+    type Error = String;
+
+    fn try_from(patterns : Vec<String>) -> Result<Self, Self::Error>
+    {
+        let set = walker::try_build_glob_set(&patterns)?;
+        Ok(FileGlobs { patterns, set })
+    }
+}
+
+impl From<FileGlobs> for Vec<String>
+{
+    fn from(globs : FileGlobs) -> Self
+    {
+        globs.patterns
+    }
+}
+
+/// An '[[override]]' section, which changes how the files matching its globs are
+/// compiled. Flags are removed before the override's own flags and defines are
+/// added, and if several overrides match a file, they are applied in order.
+#[cfg_attr(test, derive(Serialize))]
+#[derive(Deserialize, PartialEq, Default, Clone)]
+pub struct Override
+{
+    pub files : FileGlobs,
+    pub args : Option<Vec<String>>,
+    pub remove_args : Option<Vec<String>>,
+    pub defines : Option<Vec<String>>,
+    pub cstd : Option<String>,
+    pub cppstd : Option<String>
+}
+
+/// How 'quikc run' runs the executable. The working directory is relative
+/// to the project's root directory, which is also the default.
+#[cfg_attr(test, derive(Serialize))]
+#[derive(Deserialize, PartialEq, Default)]
+pub struct Run
+{
+    // the executable that runs when there are several and none was chosen on the command line
+    pub target : Option<String>,
+    pub working_dir : Option<String>,
+    // added to the environment the executable inherits
+    pub env : Option<HashMap<String, String>>
+}
+
+/// Where the source and header files of the project are found. The paths
+/// and glob patterns are relative to the project's root directory.
+#[cfg_attr(test, derive(Serialize))]
+#[derive(Deserialize, PartialEq, Default)]
+pub struct Sources
+{
+    pub dirs : Option<Vec<String>>,
+    pub include_dirs : Option<Vec<String>>,
+    // passed with '-isystem', so the headers in them do not cause warnings
+    pub system_include_dirs : Option<Vec<String>>,
+    pub globs : Option<Vec<String>>,
+    pub exclude : Option<Vec<String>>,
+    // source files that are compiled with relaxed warnings
+    pub third_party : Option<FileGlobs>
+}
+
+/// A build target declared with a '[[bin]]' or '[[lib]]' section. The
+/// objects of every source file that is not the entry point of another
+/// target are shared, so they are only compiled once and linked into
+/// each target that uses them.
+#[cfg_attr(test, derive(Serialize))]
+#[derive(Deserialize, PartialEq, Default, Clone)]
+pub struct Target
+{
+    pub name : String,
+    pub kind : Option<TargetKind>,
+    pub main : Option<String>,
+    pub sources : Option<Vec<String>>,
+    pub version : Option<String>,
+    pub exports : Option<Vec<String>>,
+    pub version_script : Option<String>,
+    pub visibility : Option<Visibility>
+}
+
+impl Target
+{
+    /// Returns the kind of file this target produces.
+    #[inline]
+    pub fn kind(&self) -> TargetKind
+    {
+        self.kind.unwrap_or_default()
+    }
+
+    /// Returns the major and minor version of the target, if it has a
+    /// valid version. The patch version is ignored as it does not take
+    /// part in the shared library's file name.
+    fn major_minor_version(&self) -> Option<(u32, u32)>
+    {
+        let mut numbers = self.version.as_ref()?.split('.');
+        let major = numbers.next()?.parse().ok()?;
+        let minor = numbers.next()?.parse().ok()?;
+
+        match numbers.next() {
+            Some(patch) if patch.parse::<u32>().is_err() => None,
+            _ if numbers.next().is_some() => None,
+            _ => Some((major, minor))
+        }
+    }
+
+    /// Returns the soname of a shared library target ('lib<name>.so.MAJOR').
+    pub fn soname(&self) -> String
+    {
+        match self.major_minor_version() {
+            Some((major, _)) => format!("lib{}.so.{}", self.name, major),
+            None => format!("lib{}.so", self.name)
+        }
+    }
+
+    /// Returns the symbolic links that point to a shared library, starting
+    /// from the one closest to the actual file. Each link points to the
+    /// previous one ('lib<name>.so' -> 'lib<name>.so.MAJOR' -> 'lib<name>.so.MAJOR.MINOR').
+    pub fn shared_library_links(&self) -> Vec<String>
+    {
+        if self.major_minor_version().is_none() {
+            return Vec::new();
+        }
+        vec![self.soname(), format!("lib{}.so", self.name)]
+    }
+
+    /// Returns the path of the file produced by this target.
+    pub fn output_file(&self) -> String
+    {
+        match self.kind() {
+            TargetKind::Executable => self.name.to_owned(),
+            TargetKind::Static => format!("lib{}.a", self.name),
+            TargetKind::Shared => match self.major_minor_version() {
+                Some((major, minor)) => format!("lib{}.so.{}.{}", self.name, major, minor),
+                None => format!("lib{}.so", self.name)
+            }
+        }
+    }
+
+    /// Returns the version script passed to the linker for a shared library.
+    /// If the target has an export list, a version script is generated from
+    /// it in '/buildinfo' so that every symbol that is not exported is hidden.
+    pub fn version_script(&self) -> Option<String>
+    {
+        if self.version_script.is_some() {
+            return self.version_script.to_owned();
+        }
+        self.exports.as_ref().map(|_| format!("{}/{}.map", BUILD_TABLE_DIRECTORY, self.name))
+    }
+}
+
 #[cfg_attr(test, derive(Serialize))]
 #[derive(Deserialize, PartialEq, Default)]
 pub struct BuildOption
@@ -67,7 +589,16 @@ pub struct BuildOption
     pub compiler : Option<Compiler>,
     pub linker : Option<Linker>,
     pub misc : Option<Misc>,
-    pub assembler : Option<Assembler>
+    pub warnings : Option<Warnings>,
+    pub assembler : Option<Assembler>,
+    pub archiver : Option<Archiver>,
+    pub sources : Option<Sources>,
+    pub run : Option<Run>,
+    pub profile : Option<HashMap<String, Profile>>,
+    #[serde(rename = "override")]
+    pub overrides : Option<Vec<Override>>,
+    pub bin : Option<Vec<Target>>,
+    pub lib : Option<Vec<Target>>
 }
 
 #[cfg_attr(test, derive(Serialize))]
@@ -78,7 +609,33 @@ pub struct Build
     pub compiler : Compiler,
     pub linker : Linker,
     pub misc : Misc,
-    pub assembler : Assembler
+    pub warnings : Warnings,
+    pub assembler : Assembler,
+    pub archiver : Archiver,
+    pub sources : Sources,
+    pub run : Run,
+    pub profile : Option<HashMap<String, Profile>>,
+    #[serde(rename = "override")]
+    pub overrides : Option<Vec<Override>>,
+    // the selected profile and its build directories are resolved in 'Build::new()'
+    #[cfg_attr(test, serde(skip_serializing))]
+    pub active_profile : ActiveProfile,
+    #[cfg_attr(test, serde(skip_serializing))]
+    pub dirs : BuildDirectories,
+    // the family and version of the C and C++ compilers
+    #[cfg_attr(test, serde(skip_serializing))]
+    pub compilers : HashMap<String, CompilerInfo>,
+    #[cfg_attr(test, serde(skip_serializing))]
+    pub environment : Environment,
+    // the default flags the C and C++ compilers do not support
+    #[cfg_attr(test, serde(skip_serializing))]
+    pub unsupported_c_flags : Vec<String>,
+    #[cfg_attr(test, serde(skip_serializing))]
+    pub unsupported_cpp_flags : Vec<String>,
+    // targets are resolved from the '[[bin]]' and '[[lib]]' sections, so they
+    // are not serialized with the rest of the configuration
+    #[cfg_attr(test, serde(skip_serializing))]
+    pub targets : Vec<Target>
 }
 
 #[cfg(feature = "quikc-nightly")]
@@ -88,89 +645,287 @@ fn warning(message : &str)
     cprintln!("<bold><yellow>warning</yellow>:</bold> {}", message);
 }
 
-impl Build
+/// Returns the given directories (or `default_directory`, if none were given)
+/// in the form './<dir>', so that the paths of the files found in them are the
+/// same as the ones of the default directories. Absolute paths are left as is.
+/// Terminates the program if one of the directories does not exist.
+fn resolve_directories(dirs : Option<Vec<String>>, default_directory : &str) -> Vec<String>
 {
-    #[inline]
-    pub fn new() -> Build
-    {
-        // Include path and source directory are required as that is where the compiler will look for files
-        if !Path::new(INCLUDE_PATH).exists() {
-            eprintln!("{}", cformat!("<bold><red>error</red>:</bold> './include' directory not found\nTerminating program."));
+    let dirs = match dirs {
+        Some(dirs) => dirs.iter()
+                          .map(|dir| match Path::new(dir).is_absolute() {
+                              true => dir.to_owned(),
+                              false => format!("./{}", compiler::without_current_dir(Path::new(dir)).to_str().unwrap())
+                          })
+                          .collect(),
+        None => vec![default_directory.to_string()]
+    };
+
+    for dir in &dirs {
+        if !Path::new(dir).is_dir() {
+            eprintln!("{}", cformat!("<bold><red>error</red>:</bold> '{}' directory not found\nTerminating program.", dir));
             std::process::exit(1);
         }
+    }
+    dirs
+}
+
+/// Returns the settings of a profile that are not inherited from another one.
+/// Only the 'dev' and 'release' profiles have them.
+fn builtin_profile(name : &str) -> Option<ActiveProfile>
+{
+    match name {
+        DEV_PROFILE => Some(ActiveProfile {
+            name : name.to_string(),
+            debug_info : true,
+            optimization_level : None,
+            lto : None,
+            defines : Vec::new(),
+            include_dirs : Vec::new()
+        }),
+        RELEASE_PROFILE => Some(ActiveProfile {
+            name : name.to_string(),
+            debug_info : false,
+            optimization_level : None,
+            lto : None,
+            defines : vec!["NDEBUG".to_string()],
+            include_dirs : Vec::new()
+        }),
+        _ => None
+    }
+}
+
+/// Resolves the settings of the profile called `name`. Settings that are not
+/// given in its '[profile.<name>]' section are inherited from the profile named
+/// by 'inherits' (or, for 'dev' and 'release', from the built in defaults).
+/// Terminates the program if the profile does not exist or inherits from itself.
+pub fn resolve_profile(name : &str, profiles : &HashMap<String, Profile>, visited : &mut Vec<String>) -> ActiveProfile
+{
+    if visited.iter().any(|visited| visited == name) {
+        error(&format!("profile '{}' inherits from itself ({} -> {})", name, visited.join(" -> "), name));
+        std::process::exit(1);
+    }
+    visited.push(name.to_string());
 
-        if !Path::new(SOURCE_DIRECTORY).exists() {
-            eprintln!("{}", cformat!("<bold><red>error</red>:</bold> './src' directory not found\nTerminating program."));
+    let profile = profiles.get(name);
+    let mut active_profile = match (profile.and_then(|profile| profile.inherits.as_ref()), builtin_profile(name)) {
+        (Some(parent), _) => resolve_profile(parent, profiles, visited),
+        (None, Some(builtin)) => builtin,
+        (None, None) if profile.is_some() => {
+            error(&format!("profile '{name}' must inherit from another profile, e.g. 'inherits = \"{RELEASE_PROFILE}\"'"));
+            std::process::exit(1);
+        },
+        (None, None) => {
+            let mut names = profiles.keys()
+                                    .map(|name| name.as_str())
+                                    .chain([DEV_PROFILE, RELEASE_PROFILE])
+                                    .collect::<Vec<&str>>();
+            names.sort();
+            names.dedup();
+            error(&format!("profile '{}' not found, available profiles are: {}", name, names.join(", ")));
             std::process::exit(1);
         }
+    };
 
-        if !Path::new(BUILD_TABLE_DIRECTORY).exists() {
-            fs::create_dir(BUILD_TABLE_DIRECTORY).expect("Failed to create directory")
+    active_profile.name = name.to_string();
+    if let Some(profile) = profile {
+        if let Some(debug_info) = profile.debug_info {
+            active_profile.debug_info = debug_info;
+        }
+        if profile.optimization_level.is_some() {
+            active_profile.optimization_level = profile.optimization_level;
+        }
+        if profile.lto.is_some() {
+            active_profile.lto = profile.lto;
+        }
+        if let Some(defines) = &profile.defines {
+            active_profile.defines = defines.to_owned();
         }
+        if let Some(include_dirs) = &profile.include_dirs {
+            active_profile.include_dirs = include_dirs.to_owned();
+        }
+    }
+    active_profile
+}
+
+/// Returns the targets that should be built. If no '[[bin]]' or '[[lib]]'
+/// sections are present, then the package itself is the only target and
+/// every object file is linked into it. Terminates the program if a target
+/// is invalid.
+/// Reads and parses 'Build.toml', terminating the program if it is missing or invalid.
+fn read_build_option() -> BuildOption
+{
+    if !Path::new(BUILD_CONFIG_FILE).exists() {
+        eprintln!("{}", cformat!("<bold><red>error</red>:</bold> 'Build.toml' not found in working directory\nTerminating program."));
+        std::process::exit(1);
+    }
+    let file_contents = fs::read_to_string(BUILD_CONFIG_FILE).expect("Failed to read from build configuration file");
+    toml::from_str(&file_contents).unwrap_or_else(|e| {
+        error(&format!("failed to parse 'Build.toml': {e}"));
+        std::process::exit(1);
+    })
+}
 
-        if !Path::new(BUILD_CONFIG_FILE).exists() {
-            eprintln!("{}", cformat!("<bold><red>error</red>:</bold> 'Build.toml' not found in working directory\nTerminating program."));
+fn resolve_targets(package : &Package, bins : Option<Vec<Target>>, libs : Option<Vec<Target>>) -> Vec<Target>
+{
+    let mut targets = Vec::new();
+
+    for mut bin in bins.unwrap_or_default() {
+        if bin.kind.is_some_and(|kind| kind != TargetKind::Executable) {
+            error(&format!("'[[bin]]' target '{}' must be an executable, use a '[[lib]]' section instead", bin.name));
             std::process::exit(1);
         }
-        let file_contents = fs::read_to_string(BUILD_CONFIG_FILE).expect("Failed to read from build configuration file");
-        let toml_config : BuildOption = toml::from_str(&file_contents).expect("Failed to parse build configuration file");
-        let mut cached_toml : Option<BuildOption> = None;
+        bin.kind = Some(TargetKind::Executable);
+        targets.push(bin);
+    }
 
-        if Path::new(BUILD_CONFIG_CACHE_FILE).exists() {
-            let cached_file_contents = fs::read_to_string(BUILD_CONFIG_CACHE_FILE)
-                                                .expect("Failed to read from cached build configuration file");
-            cached_toml = Some(toml::from_str(&cached_file_contents).expect("Failed to parse cached build configuration file"));
+    for mut lib in libs.unwrap_or_default() {
+        match lib.kind {
+            Some(TargetKind::Executable) => {
+                error(&format!("'[[lib]]' target '{}' cannot be an executable, use a '[[bin]]' section instead", lib.name));
+                std::process::exit(1);
+            },
+            Some(_) => (),
+            None => lib.kind = Some(TargetKind::Static)
+        }
+        targets.push(lib);
+    }
+
+    if targets.is_empty() {
+        targets.push(Target {
+            name : package.name.to_owned(),
+            kind : Some(package.kind.unwrap_or_default()),
+            main : None,
+            sources : None,
+            version : package.version.to_owned(),
+            exports : package.exports.to_owned(),
+            version_script : package.version_script.to_owned(),
+            visibility : package.visibility
+        });
+    }
+
+    let mut names = HashSet::new();
+    for target in &targets {
+        if target.name.is_empty() {
+            error("a '[[bin]]' or '[[lib]]' target is missing a name");
+            std::process::exit(1);
+        }
+        if !names.insert(target.name.as_str()) {
+            error(&format!("target '{}' is declared more than once", target.name));
+            std::process::exit(1);
+        }
+
+        if target.kind() == TargetKind::Shared {
+            if let Some(version) = &target.version {
+                if target.major_minor_version().is_none() {
+                    error(&format!("invalid version '{}' for target '{}', expected 'MAJOR.MINOR' or 'MAJOR.MINOR.PATCH'",
+                                   version, target.name));
+                    std::process::exit(1);
+                }
+            }
+            if target.exports.is_some() && target.version_script.is_some() {
+                error(&format!("target '{}' cannot have both 'exports' and a 'version_script'", target.name));
+                std::process::exit(1);
+            }
+        }
+        else if target.visibility.is_some() {
+            error(&format!("target '{}' cannot set a 'visibility', only shared libraries can", target.name));
+            std::process::exit(1);
+        }
+        else if target.exports.is_some() || target.version_script.is_some() {
+            error(&format!("'exports' and 'version_script' are only supported for shared libraries (target '{}')", target.name));
+            std::process::exit(1);
+        }
+
+        let files = target.main.iter().chain(target.sources.iter().flatten())
+                                      .chain(target.version_script.iter());
+        for file in files {
+            if !Path::new(file).is_file() {
+                error(&format!("file '{}' of target '{}' does not exist", file, target.name));
+                std::process::exit(1);
+            }
+        }
+    }
+    targets
+}
+
+impl Compiler
+{
+    /// Returns the arguments that are only passed to the compiler of the
+    /// given language ('c_args' or 'cpp_args').
+    #[inline]
+    pub fn language_args(&self, is_c_source_file : bool) -> Option<&Vec<String>>
+    {
+        if is_c_source_file {
+            self.c_args.as_ref()
         }
         else {
-            fs::copy(BUILD_CONFIG_FILE, BUILD_CONFIG_CACHE_FILE).expect("Failed to cache build configuration file");
+            self.cpp_args.as_ref()
+        }
+    }
+}
+
+impl Build
+{
+    #[inline]
+    pub fn new() -> Build
+    {
+        if !Path::new(BUILD_TABLE_DIRECTORY).exists() {
+            fs::create_dir(BUILD_TABLE_DIRECTORY).expect("Failed to create directory")
         }
 
+        let toml_config = read_build_option();
         let mut config = Build::default();
 
         // If a default compiler is not provided, select one automatically
         if toml_config.compiler.is_some() {
-            if toml_config.compiler.as_ref().unwrap().compiler.is_empty() {
-                config.compiler.compiler = select_default_compiler().to_string();
-                if flags()&QuikcFlags::HIDE_VERBOSE_OUTPUT == QuikcFlags::NONE {
-                    cprintln!("<bold><yellow>note</yellow>:</bold> compiler not specified in 'Build.toml', using {} as default",
-                            config.compiler.compiler);
-                }
-            }
-            else {
-                config.compiler.compiler = toml_config.compiler.as_ref().unwrap().compiler.to_owned();
-            }
             let config_ref = toml_config.compiler.as_ref().unwrap();
-            config.compiler.args = toml_config.compiler.as_ref().unwrap().args.to_owned();
+            config.compiler.compiler = config_ref.compiler.to_owned();
+            config.compiler.c_compiler = config_ref.c_compiler.to_owned();
+            config.compiler.cpp_compiler = config_ref.cpp_compiler.to_owned();
+            config.compiler.args = config_ref.args.to_owned();
+            config.compiler.c_args = config_ref.c_args.to_owned();
+            config.compiler.cpp_args = config_ref.cpp_args.to_owned();
 
             config.compiler.cppstd = Some(if config_ref.cppstd.is_none() {DEFAULT_CPP_STANDARD.to_string()} 
                                           else {format!("-std={}", config_ref.cppstd.as_ref().unwrap())});
             config.compiler.cstd = Some(if config_ref.cstd.is_none() {DEFAULT_C_STANDARD.to_string()} 
                                         else {format!("-std={}", config_ref.cstd.as_ref().unwrap())});
+            config.compiler.defines = config_ref.defines.to_owned();
             config.compiler.append_args = toml_config.compiler.as_ref().unwrap().append_args;
         }
-        else {
-            config.compiler.compiler = select_default_compiler().to_string();
-            if flags()&QuikcFlags::HIDE_VERBOSE_OUTPUT == QuikcFlags::NONE {
-                cprintln!("<bold><yellow>note</yellow>:</bold> compiler not specified in 'Build.toml', using {} as default",
-                        config.compiler.compiler);
-            }
+
+        // Every language gets its own compiler, which is derived from 'compiler' if
+        // it is not given explicitly. 'CC' and 'CXX' take precedence over both.
+        config.environment = Environment::from_env();
+        // If the C++ compiler can not be derived from 'compiler', it has to be given
+        // explicitly once there are C++ source files.
+        let compiler = Some(config.compiler.compiler.as_str()).filter(|compiler| !compiler.is_empty());
+        let c_compiler = config.environment.cc.clone()
+                                          .or(config.compiler.c_compiler.clone())
+                                          .or(compiler.map(compiler::c_counterpart));
+        let cpp_compiler = config.environment.cxx.clone()
+                                            .or(config.compiler.cpp_compiler.clone())
+                                            .or(compiler.and_then(compiler::cpp_counterpart));
+        let compiler_specified = compiler.is_some() || (c_compiler.is_some() && cpp_compiler.is_some());
+        config.compiler.c_compiler = Some(c_compiler.unwrap_or_else(|| select_default_compiler(false).to_string()));
+        config.compiler.cpp_compiler = cpp_compiler.or((!compiler_specified).then(|| select_default_compiler(true).to_string()));
+        if !compiler_specified && flags()&QuikcFlags::HIDE_VERBOSE_OUTPUT == QuikcFlags::NONE {
+            cprintln!("<bold><yellow>note</yellow>:</bold> compiler not specified in 'Build.toml', using {} and {} as default",
+                    config.c_compiler(), config.cpp_compiler());
         }
-        // Make sure the cached toml exists before comparing the files
-        if let Some(cached_toml) = cached_toml {
-            // Check if the configuration file has changed since the last build, if so we need to remove all the object
-            // files to recompile them again (as well as the 'table.toml' file)
-            if toml_config != cached_toml && Path::new(BUILD_TABLE_OBJECT_FILE_DIRECTORY).is_dir() {
-                fs::remove_dir_all(BUILD_TABLE_OBJECT_FILE_DIRECTORY).expect("Failed to remove build table object file directory");
-                fs::remove_file(BUILD_TABLE_FILE).expect("Failed to remove build table file");
-                fs::copy(BUILD_CONFIG_FILE, BUILD_CONFIG_CACHE_FILE).expect("Failed to copy from build table file");
-            }
+        if config.compiler.compiler.is_empty() {
+            config.compiler.compiler = config.c_compiler().to_string();
         }
+        config.compilers = toolchain::detect_compilers(&[config.c_compiler(), config.cpp_compiler()]);
 
         config.misc = match &toml_config.misc {
             Some(misc) => *misc,
             None => Misc {
                 optimization_level : None,
+                lto : None,
                 static_analysis_enabled : None,
+                compile_commands : None,
                 #[cfg(feature = "quikc-nightly")]
                 toggle_iwyu : None
             }
@@ -185,22 +940,92 @@ impl Build
             }
         };
 
-        config.package.name = toml_config.package.name;
-        config.package.debug_build = toml_config.package.debug_build;
+        config.overrides = toml_config.overrides;
+        config.resolve_profile_and_targets(toml_config.package, toml_config.profile, toml_config.bin, toml_config.lib);
+        if !Path::new(&config.dirs.root).exists() {
+            fs::create_dir_all(&config.dirs.root).expect("Failed to create directory")
+        }
         if toml_config.assembler.is_some() {
             config.assembler = toml_config.assembler.unwrap();
         }
-        else if !compiler::is_gcc_or_clang(&config.compiler.compiler) &&
+        else if !config.compiler_family(config.c_compiler()).is_gcc_or_clang() &&
                flags()&QuikcFlags::HIDE_VERBOSE_OUTPUT == QuikcFlags::NONE {
             error("assembler not specified in 'Build.toml' and compiler vendor is unknown. If 
                         you want to assemble any source files, please specify an assembler in 'Build.toml'");
             std::process::exit(1);
         }
         else {
-            config.assembler.assembler = config.compiler.compiler.to_owned();
+            config.assembler.assembler = config.c_compiler().to_string();
+        }
+
+
+        config.archiver = toml_config.archiver.unwrap_or_default();
+        if config.archiver.archiver.is_empty() {
+            config.archiver.archiver = DEFAULT_ARCHIVER.to_string();
+        }
+
+        // The source and include directories are required as that is where the compiler will look for files
+        config.sources = toml_config.sources.unwrap_or_default();
+        config.sources.dirs = Some(resolve_directories(config.sources.dirs.take(), SOURCE_DIRECTORY));
+        config.sources.include_dirs = Some(resolve_directories(config.sources.include_dirs.take(), INCLUDE_PATH));
+        let profile_include_dirs = std::mem::take(&mut config.active_profile.include_dirs);
+        config.active_profile.include_dirs = resolve_directories(Some(profile_include_dirs), "");
+        config.sources.system_include_dirs = config.sources.system_include_dirs.take()
+                                                                               .map(|dirs| resolve_directories(Some(dirs), ""));
+
+        config.run = toml_config.run.unwrap_or_default();
+
+        config.warnings = toml_config.warnings.unwrap_or_default();
+        for warning in config.warnings.allow.iter().chain(config.warnings.deny.iter()).flatten() {
+            let name = warning_name(warning);
+            if name.is_empty() || name.starts_with('-') || name.starts_with("no-") || name.contains(char::is_whitespace) {
+                error(&format!("invalid warning '{warning}' in '[warnings]', expected the name of a warning such as \"shadow\""));
+                std::process::exit(1);
+            }
+        }
+
+        for compiler in [config.c_compiler(), config.cpp_compiler()] {
+            if config.lto() == LtoMode::Thin && config.compiler_family(compiler) != CompilerFamily::Clang {
+                error(&format!("thin LTO is only supported by clang, but the compiler is '{compiler}'"));
+                std::process::exit(1);
+            }
+        }
+
+        // Compilers of different versions support different flags, so the default flags
+        // each compiler does not know about are left out
+        for is_c_source_file in [true, false] {
+            let compiler = if is_c_source_file {config.c_compiler()} else {config.cpp_compiler()};
+            let family = config.compiler_family(compiler);
+            if !family.is_gcc_or_clang() || !use_default_compiler_configuration(&config.compiler, is_c_source_file) {
+                continue;
+            }
+
+            let default_flags = config.default_flags(family, is_c_source_file, (config.warnings.level(), config.warnings.as_errors()));
+            let unsupported_flags = toolchain::unsupported_flags(compiler,
+                                                                 &config.compilers[compiler],
+                                                                 is_c_source_file,
+                                                                 &default_flags);
+            if !unsupported_flags.is_empty() && flags()&QuikcFlags::HIDE_VERBOSE_OUTPUT == QuikcFlags::NONE {
+                cprintln!("<bold><yellow>note</yellow>:</bold> '{}' does not support the default flags {}, they are left out",
+                        compiler, unsupported_flags.join(", "));
+            }
+            let unsupported_flags = unsupported_flags.into_iter().map(String::from).collect();
+            if is_c_source_file {
+                config.unsupported_c_flags = unsupported_flags;
+            }
+            else {
+                config.unsupported_cpp_flags = unsupported_flags;
+            }
+        }
+
+        // Object files mirror the paths of the source files, so they must be inside the project
+        for dir in config.source_dirs() {
+            if Path::new(dir).components().any(|component| matches!(component, Component::ParentDir|Component::RootDir)) {
+                error(&format!("source directory '{dir}' must be inside the project directory"));
+                std::process::exit(1);
+            }
         }
 
-        
         if flags()&QuikcFlags::HIDE_VERBOSE_OUTPUT == QuikcFlags::NONE {
             // If using nightly features, notify the user that some of the features can break compilation
             #[cfg(feature = "quikc-nightly")] 
@@ -212,7 +1037,8 @@ impl Build
             }
 
             if let Some(true) = config.compiler.append_args {
-                let args_specified = config.compiler.args.is_some();
+                let args_specified = config.compiler.args.is_some() || config.compiler.c_args.is_some() ||
+                                     config.compiler.cpp_args.is_some();
 
                 // give a warning here as this can override the default configuration (which the user probably
                 // did not mean to do)
@@ -235,87 +1061,230 @@ impl Build
         config
     }
 
+    /// Loads only what 'quikc clean' needs: the targets and the profile. Unlike
+    /// `Build::new`, the compilers are not detected or probed and no directory
+    /// is created, so nothing has to be installed and nothing is written.
+    pub fn load_targets() -> Build
+    {
+        let toml_config = read_build_option();
+        let mut config = Build::default();
+        config.resolve_profile_and_targets(toml_config.package, toml_config.profile, toml_config.bin, toml_config.lib);
+        config
+    }
+
+    /// Resolves the active profile (and with it the build directories) and the targets.
+    fn resolve_profile_and_targets(&mut self,
+                                   package : Package,
+                                   profiles : Option<HashMap<String, Profile>>,
+                                   bins : Option<Vec<Target>>,
+                                   libs : Option<Vec<Target>>)
+    {
+        // The profile given on the command line takes precedence, otherwise 'debug_build'
+        // decides between the built in ones
+        let profile_name = match selected_profile() {
+            Some(profile_name) => profile_name.to_owned(),
+            None if package.debug_build => DEV_PROFILE.to_string(),
+            None => RELEASE_PROFILE.to_string()
+        };
+        // the profile's name is used for its build directory
+        if profile_name.is_empty() || !profile_name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            error(&format!("invalid profile name '{profile_name}', only letters, digits, '-' and '_' are allowed"));
+            std::process::exit(1);
+        }
+        self.active_profile = resolve_profile(&profile_name, profiles.as_ref().unwrap_or(&HashMap::new()), &mut Vec::new());
+        self.profile = profiles;
+        self.dirs = BuildDirectories::new(&profile_name);
+
+        self.package.name = package.name;
+        self.package.debug_build = package.debug_build;
+        self.package.kind = package.kind;
+        self.package.version = package.version;
+        self.package.exports = package.exports;
+        self.package.version_script = package.version_script;
+        self.package.visibility = package.visibility;
+        self.targets = resolve_targets(&self.package, bins, libs);
+    }
+
+    /// Returns the directories that are searched for source files.
+    #[inline]
+    pub fn source_dirs(&self) -> &[String]
+    {
+        self.sources.dirs.as_deref().unwrap_or_default()
+    }
+
+    /// Returns the directories that are passed to the compiler as include paths,
+    /// the ones of the active profile come after the ones in '[sources]'.
+    #[inline]
+    pub fn include_dirs(&self) -> impl Iterator<Item = &String>
+    {
+        self.sources.include_dirs.iter().flatten().chain(&self.active_profile.include_dirs)
+    }
+
+    /// Returns the directories that are passed to the compiler as system include
+    /// paths, which are meant for third-party headers.
+    #[inline]
+    pub fn system_include_dirs(&self) -> &[String]
+    {
+        self.sources.system_include_dirs.as_deref().unwrap_or_default()
+    }
+
+    /// Returns true if `file` is part of a third-party source tree.
+    #[inline]
+    pub fn is_third_party(&self, file : &str) -> bool
+    {
+        self.sources.third_party.as_ref().is_some_and(|third_party| third_party.is_match(file))
+    }
+
+    /// Returns the compiler used for C source files.
+    #[inline]
+    pub fn c_compiler(&self) -> &str
+    {
+        self.compiler.c_compiler.as_deref().unwrap_or(&self.compiler.compiler)
+    }
+
+    /// Returns the compiler used for C++ source files.
+    #[inline]
+    pub fn cpp_compiler(&self) -> &str
+    {
+        self.compiler.cpp_compiler.as_deref().unwrap_or(&self.compiler.compiler)
+    }
+
+    /// Returns the family of `compiler`, which was detected when the build
+    /// configuration was loaded.
+    #[inline]
+    pub fn compiler_family(&self, compiler : &str) -> CompilerFamily
+    {
+        self.compilers.get(compiler).map(|info| info.family).unwrap_or_default()
+    }
+
+    /// Returns the compiler used for C++ source files, when there are some. Terminates
+    /// the program if it was neither given nor derived from 'compiler', since the C
+    /// compiler can not link the C++ standard library.
+    fn cpp_compiler_for_cpp(&self) -> &str
+    {
+        if self.compiler.cpp_compiler.is_none() {
+            error(&format!("the C++ compiler of '{}' is unknown, please set 'cpp_compiler' in the '[compiler]' section", self.compiler.compiler));
+            std::process::exit(1);
+        }
+        self.cpp_compiler()
+    }
+
+    /// Returns the compiler that compiles `file`.
+    #[inline]
+    pub fn compiler_for(&self, file : &str) -> &str
+    {
+        if compiler::is_c_source_file(file) {
+            self.c_compiler()
+        }
+        else {
+            self.cpp_compiler_for_cpp()
+        }
+    }
+
+    /// Returns the compiler that links `object_files`. The C++ compiler is
+    /// used as soon as one of them was compiled from a C++ source file, so
+    /// that the C++ standard library is linked.
+    pub fn link_driver(&self, object_files : &[String]) -> &str
+    {
+        let has_cpp_object = object_files.iter().any(|object_file| {
+            compiler::is_cpp_source_file(object_file.strip_suffix(".o").unwrap_or(object_file))
+        });
+        if has_cpp_object {
+            self.cpp_compiler_for_cpp()
+        }
+        else {
+            self.c_compiler()
+        }
+    }
+
+    /// Returns the executable 'quikc run' runs: the target called `name` if one is
+    /// given, otherwise the one set in the '[run]' section, or the only executable.
+    pub fn executable_to_run(&self, name : Option<&str>) -> Result<&Target, String>
+    {
+        let executables = self.targets.iter()
+                                      .filter(|target| target.kind() == TargetKind::Executable)
+                                      .collect::<Vec<&Target>>();
+        let names = executables.iter().map(|target| target.name.as_str()).collect::<Vec<&str>>().join(", ");
+
+        match name.or(self.run.target.as_deref()) {
+            Some(name) => match self.targets.iter().find(|target| target.name == name) {
+                Some(target) if target.kind() == TargetKind::Executable => Ok(target),
+                Some(_) => Err(format!("'{name}' is a library, only executables can be run")),
+                None => Err(format!("there is no target called '{name}', the executables are: {names}"))
+            },
+            None => match executables.as_slice() {
+                [target] => Ok(target),
+                [] => Err("the project has no executable to run".to_string()),
+                _ => Err(format!("the project has several executables ({names}), choose one with '--target <NAME>' or 'target' in the '[run]' section"))
+            }
+        }
+    }
+
+    /// Returns the '-I' flags of the include directories.
+    pub fn include_flags(&self) -> Vec<String>
+    {
+        let include_flags = self.include_dirs().map(|dir| format!("-I{dir}"));
+        let system_include_flags = self.system_include_dirs().iter()
+                                                             .flat_map(|dir| ["-isystem".to_string(), dir.to_owned()]);
+        include_flags.chain(system_include_flags).collect()
+    }
+
     /// Appends the command's arguments with the build configuration
     /// flags. This should only be used for the compiler. Can be used
     /// for the assembler as well, but only if the compiler is the same
     /// program as the assembler.
     fn append_compiler_args(&self, cmd : &mut Command, file : &str)
     {
+        let mut args = Vec::new();
 
-        // The only variable that cannot really be overridden is whether or not
-        // the build is being compiled in debug mode
-        if self.package.debug_build {
-            cmd.arg("-g");
-        }
-        else {
-            cmd.arg("-DNDEBUG");
+        // The only variables that cannot really be overridden are the ones that
+        // come from the profile the build is being compiled with
+        if self.active_profile.debug_info {
+            args.push("-g".to_string());
         }
+        let defines = self.compiler.defines.iter().flatten().chain(&self.active_profile.defines);
+        args.extend(defines.map(|define| format!("-D{define}")));
 
         let is_c_source_file = compiler::is_c_source_file(file);
+        let family = self.compiler_family(self.compiler_for(file));
 
-        if !is_c_source_file {
-            cmd.arg(self.compiler.cppstd.as_ref().unwrap());
+        // Objects are shared between targets, so if any of them is a shared
+        // library, every object must be position independent
+        let shared_libraries = self.targets.iter()
+                                           .filter(|target| target.kind() == TargetKind::Shared)
+                                           .collect::<Vec<&Target>>();
+        if !shared_libraries.is_empty() {
+            args.push("-fPIC".to_string());
+            // exported symbols are marked with the macro, since symbols with
+            // hidden visibility can not be exported by the version script
+            match family.is_gcc_or_clang() {
+                true => args.push(format!("-D{EXPORT_MACRO}=__attribute__((visibility(\"default\")))")),
+                false => args.push(format!("-D{EXPORT_MACRO}="))
+            }
         }
-        else {
-            cmd.arg(self.compiler.cstd.as_ref().unwrap());
+        // Hidden visibility is opt-in, as every exported symbol has to be declared with the macro then
+        if family.is_gcc_or_clang() && shared_libraries.iter().any(|target| target.visibility == Some(Visibility::Hidden)) {
+            args.push("-fvisibility=hidden".to_string());
+            if !is_c_source_file {
+                args.push("-fvisibility-inlines-hidden".to_string());
+            }
         }
 
+        args.push(self.get_standard(file));
+
         // If the default configuration variable is set to true, use the default arguments
         // (note this doesnt support MSVC)
-        if use_default_compiler_configuration(&self.compiler) {
-            if !is_c_source_file {
-                cmd.args(GCC_AND_CLANG_CPP_DIALECT_OPTIONS);
-            }
+        if use_default_compiler_configuration(&self.compiler, is_c_source_file) {
+            // the default flags the compiler does not support are left out
+            let unsupported_flags = self.unsupported_flags(is_c_source_file);
+            let default_flags = self.default_flags(family, is_c_source_file, self.warning_settings(file));
+            args.extend(default_flags.into_iter()
+                                     .filter(|flag| !unsupported_flags.iter().any(|unsupported| unsupported == flag))
+                                     .map(String::from));
 
             // Default configuration only supported on gcc and clang
-            if compiler::is_gcc_or_clang(&self.compiler.compiler) {
-
-                cmd.args(GCC_AND_CLANG_DIALECT_OPTIONS);
-
-                // Check if the optimization level is not specified, if not
-                // then apply the regular optimizations on release builds only
-                if self.misc.optimization_level.is_none() {
-                    if !self.package.debug_build {
-                        cmd.args(GCC_AND_CLANG_OPTIMIZATION_OPTIONS);
-                    }
-                }
-                // otherwise leave it up to the user to specify the optimization level
-                else {
-                    let opt_level = self.misc.optimization_level.unwrap();
-                    if opt_level == 2 {
-                        cmd.args(GCC_AND_CLANG_OPTIMIZATION_OPTIONS);
-                    }
-
-                    else if opt_level == 3 {
-                        cmd.args(GCC_AND_CLANG_ENHANCED_OPTIMIZATION_OPTIONS);
-                    }
-                }
-
-                
-                match self.compiler.compiler.as_str() {
-                    // append gcc exclusive warnings/dialect options to the command
-                    "gcc"|"g++" => {
-                        cmd.args(GCC_COMPILER_NONEXCLUSIVE_WARNINGS);
-                        if is_c_source_file {
-                            cmd.args(GCC_COMPILER_C_EXCLUSIVE_WARNINGS);
-                        }
-                        else {
-                            cmd.args(GCC_COMPILER_CPP_DIALECT_OPTIONS);
-                            cmd.args(GCC_COMPILER_CPP_EXCLUSIVE_WARNINGS);
-                        }
-                        if self.misc.static_analysis_enabled.is_some() && self.misc.static_analysis_enabled.unwrap() {
-                            cmd.args(GCC_STATIC_ANALYSIS_OPTIONS);
-                        }
-                    }
-                    // append clang exclusive warnings/dialect options to the command
-                    "clang"|"clang++" => {
-                        cmd.args(CLANG_COMPILER_NONEXCLUSIVE_WARNINGS);
-                        if !is_c_source_file {
-                            cmd.args(CLANG_COMPILER_CPP_WARNINGS);
-                        }
-                    },
-                    _ => ()
-                }   
+            if family.is_gcc_or_clang() {
+                args.extend(self.optimization_args(family).into_iter().map(String::from));
             }
             else if flags()&QuikcFlags::HIDE_VERBOSE_OUTPUT == QuikcFlags::NONE {
                 cprintln!("<bold><yellow>note</yellow>:</bold> cannot use default configuration because
@@ -324,39 +1293,194 @@ impl Build
 
             // append arguments if the flag is set
             if let Some(true) = self.compiler.append_args {
-                cmd.args(self.compiler.args.as_ref().unwrap().iter());
+                args.extend(self.compiler.args.iter().flatten().cloned());
+                args.extend(self.compiler.language_args(is_c_source_file).into_iter().flatten().cloned());
             }
-            return;
         }
-
         // If default configuration is not set, then use the user's custom flags
-        if let Some(compiler_args) = &self.compiler.args {
-            cmd.args(compiler_args.iter());
+        else {
+            args.extend(self.compiler.args.iter().flatten().cloned());
+            args.extend(self.compiler.language_args(is_c_source_file).into_iter().flatten().cloned());
         }
 
+        // The allowed and denied warnings come after the other warnings, since
+        // the last flag for a warning decides what happens to it
+        if family.is_gcc_or_clang() {
+            args.extend(self.warnings.policy_args(self.is_third_party(file)));
+        }
+
+        // Overrides for the file's path change everything but the environment's flags
+        for file_override in self.overrides_for(file) {
+            let removed_args = file_override.remove_args.as_deref().unwrap_or_default();
+            args.retain(|arg| !removed_args.contains(arg));
+            args.extend(file_override.defines.iter().flatten().map(|define| format!("-D{define}")));
+            args.extend(file_override.args.iter().flatten().cloned());
+        }
+
+        cmd.args(args);
+        // Flags from the environment come last, so that they take precedence
+        cmd.args(self.environment.compiler_flags(is_c_source_file));
     }
 
-    /// Returns a command that invokes the compiler with the appropriate
-    /// arguments given from the build configuration.
+    /// Returns the '[[override]]' sections that match `file`, in the order they are declared.
+    fn overrides_for<'a>(&'a self, file : &'a str) -> impl Iterator<Item = &'a Override>
+    {
+        self.overrides.iter().flatten().filter(move |file_override| file_override.files.is_match(file))
+    }
+
+    /// Returns the warning level and whether warnings are errors for `file`. Third-party
+    /// source files are compiled with relaxed warnings that are not errors, unless
+    /// the warning level is already lower.
+    fn warning_settings(&self, file : &str) -> (WarningLevel, bool)
+    {
+        match self.warnings.level() {
+            WarningLevel::Strict | WarningLevel::Default if self.is_third_party(file) => (WarningLevel::Relaxed, false),
+            level if self.is_third_party(file) => (level, false),
+            level => (level, self.warnings.as_errors())
+        }
+    }
+
+    /// Returns the default dialect options and warnings for source files of the
+    /// given language, when they are compiled by a compiler of `family` with the
+    /// given warning level.
+    fn default_flags(&self,
+                     family : CompilerFamily,
+                     is_c_source_file : bool,
+                     (level, warnings_as_errors) : (WarningLevel, bool)) -> Vec<&'static str>
+    {
+        let mut default_flags = Vec::new();
+        if !is_c_source_file {
+            default_flags.extend(GCC_AND_CLANG_CPP_DIALECT_OPTIONS);
+        }
+        if !family.is_gcc_or_clang() {
+            return default_flags;
+        }
+        default_flags.extend(GCC_AND_CLANG_DIALECT_OPTIONS);
+
+        match (family, level) {
+            (_, WarningLevel::None) => (),
+            (_, WarningLevel::Relaxed) => default_flags.extend(GCC_AND_CLANG_RELAXED_WARNINGS),
+            // gcc exclusive warnings
+            (CompilerFamily::Gcc, _) => {
+                default_flags.extend(GCC_COMPILER_NONEXCLUSIVE_WARNINGS);
+                if is_c_source_file {
+                    default_flags.extend(GCC_COMPILER_C_EXCLUSIVE_WARNINGS);
+                }
+                else {
+                    default_flags.extend(GCC_COMPILER_CPP_EXCLUSIVE_WARNINGS);
+                }
+                if level == WarningLevel::Strict {
+                    default_flags.extend(GCC_STRICT_WARNINGS);
+                    if is_c_source_file {
+                        default_flags.extend(GCC_STRICT_C_WARNINGS);
+                    }
+                }
+            },
+            // clang exclusive warnings
+            _ => {
+                default_flags.extend(CLANG_COMPILER_NONEXCLUSIVE_WARNINGS);
+                if !is_c_source_file {
+                    default_flags.extend(CLANG_COMPILER_CPP_WARNINGS);
+                }
+                if level == WarningLevel::Strict {
+                    default_flags.extend(CLANG_STRICT_WARNINGS);
+                }
+            }
+        }
+        if warnings_as_errors {
+            default_flags.push("-Werror");
+        }
+
+        // gcc exclusive dialect options
+        if family == CompilerFamily::Gcc {
+            if !is_c_source_file {
+                default_flags.extend(GCC_COMPILER_CPP_DIALECT_OPTIONS);
+            }
+            if self.misc.static_analysis_enabled.is_some() && self.misc.static_analysis_enabled.unwrap() {
+                default_flags.extend(GCC_STATIC_ANALYSIS_OPTIONS);
+            }
+        }
+        default_flags
+    }
+
+    /// Returns the default flags that the compiler of the given language does not support.
+    #[inline]
+    pub fn unsupported_flags(&self, is_c_source_file : bool) -> &[String]
+    {
+        if is_c_source_file {
+            &self.unsupported_c_flags
+        }
+        else {
+            &self.unsupported_cpp_flags
+        }
+    }
+
+    /// Returns the optimization level the project is compiled with. Unless
+    /// the profile or '[misc]' specifies one, builds without debug information
+    /// are optimized with '-O2' and the others are not optimized at all.
+    pub fn optimization_level(&self) -> Option<OptimizationLevel>
+    {
+        self.active_profile.optimization_level
+            .or(self.misc.optimization_level)
+            .or((!self.active_profile.debug_info).then_some(OptimizationLevel::Default))
+    }
+
+    /// Returns the kind of link time optimization. Unless the profile or '[misc]'
+    /// specifies one, it is only used on builds without debug information.
+    pub fn lto(&self) -> LtoMode
+    {
+        self.active_profile.lto
+            .or(self.misc.lto)
+            .unwrap_or(if self.active_profile.debug_info {LtoMode::Off} else {LtoMode::Full})
+    }
+
+    /// Returns the optimization level and link time optimization flags, which
+    /// are passed to both the compiler and the linker.
+    fn optimization_args(&self, family : CompilerFamily) -> Vec<&'static str>
+    {
+        self.optimization_level().map(OptimizationLevel::flag).into_iter()
+            .chain(self.lto().flag(family))
+            .collect()
+    }
+
+    /// Returns a command that invokes the compiler of `file`'s language with
+    /// the appropriate arguments given from the build configuration.
     pub fn execute_compiler_with_build_info(&self, file : &str) -> Command
     {
 
-        let mut cmd = Command::new(&self.compiler.compiler);
+        let mut cmd = compiler::compiler_command(self.compiler_for(file));
         self.append_compiler_args(&mut cmd, file);
         cmd
     }
 
     /// Returns a command that invokes the linker with the appropriate
-    /// arguments given from the build configuration.
-    pub fn execute_linker_with_build_info(&self) -> Command
+    /// arguments given from the build configuration to link `object_files`
+    /// into `target`. The object files themselves are not added.
+    pub fn execute_linker_with_build_info(&self, target : &Target, object_files : &[String]) -> Command
     {
         let linker_libraries = self.linker.libraries.as_ref();
+        let link_driver = self.link_driver(object_files);
 
-        let mut cmd = Command::new(&self.compiler.compiler);
+        let mut cmd = compiler::compiler_command(link_driver);
+
+        if target.kind() == TargetKind::Shared {
+            cmd.arg("-shared");
+            cmd.arg(format!("-Wl,-soname,{}", target.soname()));
+            if let Some(version_script) = target.version_script() {
+                cmd.arg(format!("-Wl,--version-script={version_script}"));
+            }
+        }
+
+        // the link time optimization uses the same settings the objects were compiled
+        // with, so they are passed even if the linker arguments are the user's own
+        let family = self.compiler_family(link_driver);
+        if family.is_gcc_or_clang() {
+            cmd.args(self.optimization_args(family));
+        }
 
         if linker::use_default_linker_configuration(&self.linker) {
-            // apply linker optimizations on release builds only
-            if !self.package.debug_build {
+            // apply linker optimizations on builds without debug information only
+            if !self.active_profile.debug_info {
                 cmd.args(GCC_AND_CLANG_LINKER_OPTIONS);
             }
             // append arguments if the flag is set
@@ -368,6 +1492,7 @@ impl Build
             let linker_args = self.linker.args.as_ref().unwrap();
             cmd.args(linker_args.iter());
         }
+        cmd.args(self.environment.ldflags.iter());
 
         // add any libraries to link with to the command, if there are any
         if let Some(linker_libraries) = linker_libraries {
@@ -377,15 +1502,32 @@ impl Build
 
     }
 
-    /// Returns the appropriate standard to use for the given file.
-    #[inline]
-    #[allow(dead_code)]
-    pub fn get_standard(&self, file_name : &str) -> &String {
-        if compiler::is_c_source_file(file_name) {
-            self.compiler.cstd.as_ref().unwrap()
-        }
-        else {
-            self.compiler.cppstd.as_ref().unwrap()
+    /// Returns a command that invokes the archiver with the appropriate
+    /// arguments given from the build configuration.
+    pub fn execute_archiver_with_build_info(&self) -> Command
+    {
+        let mut cmd = Command::new(&self.archiver.archiver);
+
+        match &self.archiver.args {
+            Some(args) if !args.is_empty() => cmd.args(args.iter()),
+            _ => cmd.args(AR_OPTIONS)
+        };
+        cmd
+    }
+
+    /// Returns the appropriate standard to use for the given file. The last
+    /// override that matches the file and sets a standard for its language wins.
+    pub fn get_standard(&self, file_name : &str) -> String
+    {
+        let is_c_source_file = compiler::is_c_source_file(file_name);
+        let overridden_standard = self.overrides_for(file_name)
+                                      .filter_map(|file_override| if is_c_source_file {file_override.cstd.as_ref()}
+                                                                  else {file_override.cppstd.as_ref()})
+                                      .last();
+        match overridden_standard {
+            Some(standard) => format!("-std={standard}"),
+            None if is_c_source_file => self.compiler.cstd.clone().unwrap(),
+            None => self.compiler.cppstd.clone().unwrap()
         }
     }
 
@@ -393,7 +1535,7 @@ impl Build
     /// arguments given from the build configuration.
     pub fn execute_assembler_with_build_info(&self, file : &str) -> Command
     {
-        let mut cmd = Command::new(&self.assembler.assembler);
+        let mut cmd = compiler::compiler_command(&self.assembler.assembler);
 
         // if a default assembler configuration is being used, then we can just
         // append the compiler arguments to the assembler (since the assembler 
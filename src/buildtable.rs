@@ -1,29 +1,93 @@
-use std::{path::{PathBuf, Path}, fs::{File, self, Metadata}, time::UNIX_EPOCH, sync::{atomic::{AtomicBool, Ordering}}, collections::{HashSet, HashMap}, io::Write};
+use std::{path::{PathBuf, Path}, fs::{File, self, Metadata}, time::UNIX_EPOCH, process::Command, sync::{atomic::{AtomicBool, Ordering}}, collections::{HashSet, HashMap}, io::Write};
 
-use const_format::concatcp;
 use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
+use serde_derive::Deserialize;
 use walkdir::WalkDir;
+use xxhash_rust::xxh3::xxh3_64;
 
-use crate::{compiler::{self, INCLUDE_PATH}, flags, QuikcFlags};
+use crate::{build::Build, compiler, depfile, flags, QuikcFlags};
 use bitflags::bitflags;
 
 pub const BUILD_TABLE_DIRECTORY : &str = "./buildinfo";
-pub const BUILD_TABLE_OBJECT_FILE_DIRECTORY : &str = concatcp!(BUILD_TABLE_DIRECTORY, "/obj");
-pub const BUILD_TABLE_FILE : &str = concatcp!(BUILD_TABLE_DIRECTORY, "/table");
-pub const BUILD_TABLE_DEPS_DIRECTORY : &str = concatcp!(BUILD_TABLE_DIRECTORY, "/deps");
-pub const BUILD_TABLE_ASM_DIRECTORY : &str = concatcp!(BUILD_TABLE_DIRECTORY, "/asm");
+
+/// The directories and files the build information of a profile is kept in.
+/// Every profile has its own ('/buildinfo/<profile>'), so switching between
+/// profiles does not cause anything to be rebuilt.
+#[derive(Deserialize, Default, Clone, PartialEq, Eq, Debug)]
+pub struct BuildDirectories
+{
+    pub root : String,
+    pub obj : String,
+    pub deps : String,
+    pub asm : String,
+    pub table : String,
+    pub links : String
+}
+
+impl BuildDirectories
+{
+    pub fn new(profile : &str) -> BuildDirectories
+    {
+        let root = format!("{BUILD_TABLE_DIRECTORY}/{profile}");
+        BuildDirectories {
+            obj : format!("{root}/obj"),
+            deps : format!("{root}/deps"),
+            asm : format!("{root}/asm"),
+            table : format!("{root}/table"),
+            links : format!("{root}/links"),
+            root
+        }
+    }
+}
 
 bitflags! {
     struct BuildTableFlags : u8 {
         const NONE = 0;
         const ANY_DEPENDENCIES_CHANGED = 1 << 0;
+        const RECORDS_REFRESHED = 1 << 1;
+    }
+}
+
+/// The state of a file at the time it was last built. For source files,
+/// `command` is the fingerprint of the command line that compiled them,
+/// for every other file it is 0.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct FileRecord
+{
+    pub modified : u64,
+    pub hash : u64,
+    pub command : u64
+}
+
+impl FileRecord
+{
+    /// Parses a record written as 'modified:hash:command'. Tables written by
+    /// older versions of quikc may only store the modification time (and hash),
+    /// in which case the missing values are left as 0 so the file is treated as
+    /// changed unless its modification time is the same.
+    fn parse(value : &str) -> Option<FileRecord>
+    {
+        let mut values = value.split(':');
+        let modified = values.next()?.parse().ok()?;
+        let hash = values.next().map_or(Some(0), |hash| hash.parse().ok())?;
+        let command = values.next().map_or(Some(0), |command| command.parse().ok())?;
+
+        Some(FileRecord {
+            modified,
+            hash,
+            command
+        })
     }
 }
 
 pub struct BuildTable
 {
-    table : HashMap<String, u64>,
-    flags : BuildTableFlags
+    table : HashMap<String, FileRecord>,
+    flags : BuildTableFlags,
+    dirs : BuildDirectories,
+    // if there are system include directories, the dependency files list every system
+    // header as well, so only the dependencies in these directories are kept
+    tracked_dirs : Option<Vec<String>>
 }
 
 #[inline]
@@ -32,21 +96,83 @@ pub fn get_duration_since_modified(metadata : &Metadata) -> u64
     (metadata.modified().unwrap().duration_since(UNIX_EPOCH).unwrap().as_millis()) as u64
 }
 
-/// Returns true if the file has been modified since the last build.
+/// Returns a digest of the file's contents.
+#[inline]
+pub fn hash_file(path : &Path) -> u64
+{
+    xxh3_64(&fs::read(path).expect("Failed to read file"))
+}
+
+/// Returns a fingerprint of the command's program and arguments.
+pub fn command_fingerprint(cmd : &Command) -> u64
+{
+    let mut command_line = cmd.get_program().to_string_lossy().into_owned().into_bytes();
+    for arg in cmd.get_args() {
+        // separate the arguments so that 'a b' and 'ab' are not the same command
+        command_line.push(0);
+        command_line.extend_from_slice(arg.to_string_lossy().as_bytes());
+    }
+    xxh3_64(&command_line)
+}
+
+/// Reads the records of each target's output file, keyed by the output file.
+/// `command` is the fingerprint of the linker (or archiver) command that
+/// produced it, which tables written by older versions of quikc do not store.
+pub fn read_link_table(dirs : &BuildDirectories) -> HashMap<String, FileRecord>
+{
+    let file_contents = match fs::read_to_string(&dirs.links) {
+        Ok(file_contents) => file_contents,
+        Err(_) => return HashMap::new()
+    };
+
+    file_contents.lines()
+                 .filter_map(|line| line.rsplit_once('='))
+                 .filter_map(|(output_file, record)| Some((output_file.to_string(), FileRecord::parse(record)?)))
+                 .collect()
+}
+
+/// Writes the records of each target's output file.
+pub fn write_link_table(dirs : &BuildDirectories, link_table : &HashMap<String, FileRecord>)
+{
+    let mut f = File::create(&dirs.links).expect("Failed to create link table file");
+    for (k, v) in link_table {
+        f.write_all(format!("{k}={}:{}:{}\n", v.modified, v.hash, v.command).as_bytes()).expect("Failed to write to link table file");
+    }
+}
+
+/// Returns the file's current record. Hashing is only done if the
+/// modification time differs from the one in `old_record`, as comparing
+/// times is much cheaper. Otherwise, the old hash is reused.
+pub fn current_file_record(path : &Path, old_record : Option<&FileRecord>) -> FileRecord
+{
+    let modified = get_duration_since_modified(&path.metadata().expect("Failed to retrieve metadata from file"));
+    match old_record {
+        Some(old_record) if old_record.modified == modified => *old_record,
+        _ => FileRecord {
+            modified,
+            hash : hash_file(path),
+            command : 0
+        }
+    }
+}
+
+/// Returns true if the file has been modified since the last build. A
+/// file only counts as modified if its contents (or for source files, the
+/// command line that compiles it) changed, so touching a file or restoring
+/// it with a fresh modification time does not.
 fn file_modified_since_last_build(source_file_path : &Path, 
                                   source_file_name : &str,
                                   is_header_file : bool,
-                                  time : u64,
-                                  old_table : &HashMap<String, u64>) -> bool
+                                  record : &FileRecord,
+                                  old_table : &HashMap<String, FileRecord>,
+                                  dirs : &BuildDirectories) -> bool
 {
 
-    // check if value exists in the table, if so, compare the times,
+    // check if value exists in the table, if so, compare the hashes,
     // if they are the same, then the source file has not been modified,
     // so recompilation is not necessary. Otherwise, it is
-    if old_table.contains_key(source_file_name) {
-        let old_value = old_table[source_file_name];
-
-        if old_value != time {
+    if let Some(old_record) = old_table.get(source_file_name) {
+        if old_record.hash != record.hash || old_record.command != record.command {
             return true;
         }
         
@@ -56,18 +182,19 @@ fn file_modified_since_last_build(source_file_path : &Path,
             // If the user wants an assembly output, check if an assembly version already exists
             // and if it does, then recompilation is not necessary
             if flags()&QuikcFlags::ASSEMBLE == QuikcFlags::ASSEMBLE {
-                let assembly_file = compiler::to_output_file(source_file_path, BUILD_TABLE_ASM_DIRECTORY, "s");
+                let assembly_file = compiler::to_output_file(source_file_path, &dirs.asm, "s");
                 if Path::new(&assembly_file).exists() {
                     return false;
                 }
             }
 
-            // If the object file does exist, compilation was most likely successful, if not
-            // then re-compilation is necessary
+            // If the object file (and the dependency file written along with it) does exist,
+            // compilation was most likely successful, if not then re-compilation is necessary
             else {
-                let object_file = compiler::to_output_file(source_file_path, BUILD_TABLE_OBJECT_FILE_DIRECTORY, "o");
+                let object_file = compiler::to_output_file(source_file_path, &dirs.obj, "o");
+                let dep_file = compiler::to_output_file(source_file_path, &dirs.deps, "d");
                 
-                if Path::new(&object_file).exists() {
+                if Path::new(&object_file).exists() && Path::new(&dep_file).exists() {
                     return false;
                 }
             }
@@ -85,25 +212,35 @@ impl BuildTable
     /// Creates a new build table. This should only be created once
     /// at the beginning of the program as creating it is expensive.
     /// It will initialize the old table's state to the contents in
-    /// '/buildinfo/table'. This is done so that the current table
+    /// the profile's 'table' file. This is done so that the current table
     /// can be modified and compared to the old table.
-    pub fn new(old_table : &mut HashMap<String, u64>) -> BuildTable
+    pub fn new(build_config : &Build, old_table : &mut HashMap<String, FileRecord>) -> BuildTable
     {
+        // headers can be in the include directories or next to the source files
+        let header_dirs = build_config.include_dirs()
+                                      .chain(build_config.system_include_dirs())
+                                      .chain(build_config.source_dirs())
+                                      .collect::<Vec<&String>>();
+        let tracked_dirs = (!build_config.system_include_dirs().is_empty()).then(|| {
+            header_dirs.iter().map(|dir| dir.to_string()).collect()
+        });
+
+        let dirs = build_config.dirs.clone();
 
         // Create build object file directory
-        if !Path::new(BUILD_TABLE_OBJECT_FILE_DIRECTORY).is_dir() {
-            std::fs::create_dir(BUILD_TABLE_OBJECT_FILE_DIRECTORY).expect("Failed to create build object file directory");
+        if !Path::new(&dirs.obj).is_dir() {
+            std::fs::create_dir_all(&dirs.obj).expect("Failed to create build object file directory");
         }
 
-        if !Path::new(BUILD_TABLE_DEPS_DIRECTORY).is_dir() {
-            std::fs::create_dir(BUILD_TABLE_DEPS_DIRECTORY).expect("Failed to create build dependencies directory");
+        if !Path::new(&dirs.deps).is_dir() {
+            std::fs::create_dir_all(&dirs.deps).expect("Failed to create build dependencies directory");
         }
 
-        if !Path::new(BUILD_TABLE_FILE).is_file() {
-            File::create(BUILD_TABLE_FILE).expect("Failed to create build table file");
+        if !Path::new(&dirs.table).is_file() {
+            File::create(&dirs.table).expect("Failed to create build table file");
         }
 
-        let file_contents = fs::read_to_string(BUILD_TABLE_FILE).expect("Failed to read file");
+        let file_contents = fs::read_to_string(&dirs.table).expect("Failed to read file");
         let mut table = HashMap::new();
         let mut flags = BuildTableFlags::NONE;
         
@@ -111,9 +248,9 @@ impl BuildTable
         // made and can simply just add the all header files to the table
         if !file_contents.is_empty() {
             for line in file_contents.lines() {
-                let split : Vec<&str> = line.split('=').collect();
-                let key = split[0].to_string();
-                let value = split[1].parse::<u64>().unwrap();
+                let (key, value) = line.rsplit_once('=').expect("Malformed build table entry");
+                let key = key.to_string();
+                let value = FileRecord::parse(value).expect("Malformed build table entry");
                 
                 let exists = Path::new(&key).exists();
 
@@ -131,8 +268,8 @@ impl BuildTable
                 }
             }
         
-            // recursively walk through the include directory
-            for path in WalkDir::new(INCLUDE_PATH) {
+            // recursively walk through the include (and source) directories
+            for path in header_dirs.iter().flat_map(WalkDir::new) {
                 let path = path.unwrap().path().to_path_buf();
                 if path.is_file() {
                     let path_str = path.to_str().unwrap();
@@ -143,25 +280,34 @@ impl BuildTable
                         let path_str_no_relative = if let Some(stripped) = path_str.strip_prefix("./") 
                             { stripped } else { path_str };
                         
-                        // compare the header's file current modification time with the time set in the table,
-                        // if the current time is newer, then we can insert the new time into the table and
+                        // compare the header's current contents with the ones recorded in the table,
+                        // if they differ, then we can insert the new record into the table and
                         // mark 'any_dependencies_changed' as true
-                        let metadata = path.metadata().unwrap();
-                        let duration = get_duration_since_modified(&metadata);
+                        let old_record = old_table.get(path_str_no_relative).copied();
+                        let record = current_file_record(&path, old_record.as_ref());
                         if file_modified_since_last_build(&path, 
                                                                 path_str_no_relative, 
                                                                     true,
-                                                                    duration,
-                                                                        old_table) {
-                            table.insert(path_str_no_relative.to_string(), duration);
+                                                                    &record,
+                                                                        old_table,
+                                                                        &dirs) {
+                            table.insert(path_str_no_relative.to_string(), record);
                             flags |= BuildTableFlags::ANY_DEPENDENCIES_CHANGED;
                         }
+                        // the header was only touched, so refresh its modification time to avoid
+                        // hashing it again. The old table is updated as well so that the source
+                        // files depending on it can use the cheap comparison.
+                        else if old_record.is_some_and(|old_record| old_record.modified != record.modified) {
+                            table.insert(path_str_no_relative.to_string(), record);
+                            old_table.insert(path_str_no_relative.to_string(), record);
+                            flags |= BuildTableFlags::RECORDS_REFRESHED;
+                        }
                     }
                 }
             }
         }
         else {
-            for path in WalkDir::new(INCLUDE_PATH) {
+            for path in header_dirs.iter().flat_map(WalkDir::new) {
                 let path = path.unwrap().path().to_path_buf();
 
                 if path.is_file() {
@@ -174,9 +320,7 @@ impl BuildTable
 
                         // since the build table is empty, there is nothing to compare to, so we just insert
                         // the file
-                        let metadata = path.metadata().unwrap();
-                        let duration = get_duration_since_modified(&metadata);
-                        table.insert(path_str_no_relative.to_string(), duration);
+                        table.insert(path_str_no_relative.to_string(), current_file_record(&path, None));
                         flags |= BuildTableFlags::ANY_DEPENDENCIES_CHANGED;
                     }
                 }
@@ -185,7 +329,9 @@ impl BuildTable
 
         BuildTable {
             table,
-            flags
+            flags,
+            dirs,
+            tracked_dirs
         }
     }
 
@@ -194,61 +340,74 @@ impl BuildTable
     /// often. Future versions may include a flag to count system dependencies as well.
     pub fn get_file_dependencies(&self, source_file_name : &str) -> HashSet<String>
     {
-        let path = Path::new(source_file_name);
-        let file_name = path.file_stem().unwrap().to_str().unwrap();
-        let dep_name = BUILD_TABLE_DEPS_DIRECTORY.to_string() + "/" + file_name + ".d";
+        let dep_name = compiler::to_output_file(Path::new(source_file_name), &self.dirs.deps, "d");
 
         if !Path::new(&dep_name).is_file() {
             return HashSet::new();
         }
 
-        let dependencies_str_tmp = fs::read_to_string(&dep_name).unwrap();
-        
-        let dependencies = move || {
-            let mut count = 0;
-            // at least for clang and gcc, they output the dependencies in a format like:
-            // source.o: source.c <dependencies>
-            // This follows the make format. As you can see from this, we need to skip the
-            // first two spaces to get the actual list of dependencies.
-            for (i, c) in dependencies_str_tmp.chars().enumerate() {
-                if c == ' ' {
-                    count += 1;
-                }
-                if count == 2 {
-                    return dependencies_str_tmp[i + 1..].split_whitespace()
-                                                        .map(|s| s.to_string())
-                                                        .collect::<HashSet<_>>();
-                }
-            }
-            HashSet::new()
-        };
-        dependencies()
+        let contents = fs::read_to_string(&dep_name).unwrap();
+        let source_file_name_no_relative = source_file_name.strip_prefix("./").unwrap_or(source_file_name);
 
+        // the source file itself is listed as a prerequisite of its object file,
+        // but it is already checked separately
+        depfile::parse(&contents).into_iter()
+                                 .flat_map(|rule| rule.prerequisites)
+                                 .filter(|dependency| dependency.strip_prefix("./").unwrap_or(dependency) != source_file_name_no_relative)
+                                 .filter(|dependency| self.is_tracked_dependency(dependency))
+                                 .collect()
+    }
+
+    /// Returns true if changes to `dependency` should cause a recompilation. Headers
+    /// outside of the project (such as the C library's) are only listed when there
+    /// are system include directories, and they are not tracked.
+    fn is_tracked_dependency(&self, dependency : &str) -> bool
+    {
+        let Some(tracked_dirs) = &self.tracked_dirs else {
+            return true;
+        };
+        let dependency = compiler::without_current_dir(Path::new(dependency));
+        dependency.is_relative() ||
+        tracked_dirs.iter().any(|dir| dependency.starts_with(compiler::without_current_dir(Path::new(dir))))
     }
 
     /// Returns true if 'source_file_path' needs to be recompiled.
     /// Header files do not count as a source file, only files with
-    /// a .c, .cpp, .cxx, .cc extension count. 
+    /// a .c, .cpp, .cxx, .cc extension count. `command` is the
+    /// fingerprint of the command line that compiles the file.
     pub fn needs_to_be_recompiled(&mut self,
                                   source_file_path : &Path,
-                                  old_table : &HashMap<String, u64>) -> bool
+                                  command : u64,
+                                  old_table : &HashMap<String, FileRecord>) -> bool
     {
         let source_file_name = source_file_path.to_str().unwrap();
 
 
-        // check if source file has changed, if so, we don't need to check if any of the dependencies
-        // changed which is best case scenario.
-        let source_modified_duration = get_duration_since_modified(&source_file_path.metadata().unwrap());
+        // check if source file (or its command line) has changed, if so, we don't need to check
+        // if any of the dependencies changed which is best case scenario.
+        let old_record = old_table.get(source_file_name);
+        let record = FileRecord {
+            command,
+            ..current_file_record(source_file_path, old_record)
+        };
         if file_modified_since_last_build(source_file_path, 
                                                source_file_name, 
                                                false, 
-                                               source_modified_duration,
-                                               old_table) {
-            // insert the new modification time
-            self.table.insert(source_file_name.to_string(), source_modified_duration);
+                                               &record,
+                                               old_table,
+                                               &self.dirs) {
+            // insert the new record
+            self.table.insert(source_file_name.to_string(), record);
             return true;
         }
-        else if self.flags&BuildTableFlags::ANY_DEPENDENCIES_CHANGED == BuildTableFlags::ANY_DEPENDENCIES_CHANGED {
+
+        // the source file was only touched, so refresh its modification time
+        if old_record.is_some_and(|old_record| old_record.modified != record.modified) {
+            self.table.insert(source_file_name.to_string(), record);
+            self.flags |= BuildTableFlags::RECORDS_REFRESHED;
+        }
+
+        if self.flags&BuildTableFlags::ANY_DEPENDENCIES_CHANGED == BuildTableFlags::ANY_DEPENDENCIES_CHANGED {
             let dependencies = self.get_file_dependencies(source_file_name);
             const LARGE_NUMBER_OF_FILES : usize = 50;
 
@@ -258,58 +417,70 @@ impl BuildTable
                 let recompile = AtomicBool::new(false);
                 // check if any dependencies have changed for the source file 
                 dependencies.par_iter().for_each(|dependency| {
-                    // sometimes the compiler shows '\' for line breaks, so we need to ignore those
-                    if dependency != "\\" {
-                        let dependency_path = PathBuf::from(dependency);
-                        // some dependencies may have been deleted or moved to different locations
-                        // since last compilation so its important to check if it exists first
-                        if dependency_path.exists() {
-                            let source_metadata = dependency_path.metadata().expect("Failed to retrieve metadata from file");
-                            let duration = get_duration_since_modified(&source_metadata);
-                            if file_modified_since_last_build(&dependency_path, 
-                                                                    dependency, 
-                                                                    true,
-                                                                    duration,
-                                                                        old_table) {
-                                recompile.store(true, Ordering::Relaxed);
-                                
-                            }
-                        }
-                        else {
-                            // dependency was deleted or moved so we need to recompile 
-                            // the file
+                    let dependency_path = PathBuf::from(dependency);
+                    // some dependencies may have been deleted or moved to different locations
+                    // since last compilation so its important to check if it exists first
+                    if dependency_path.exists() {
+                        let record = current_file_record(&dependency_path, old_table.get(dependency));
+                        if file_modified_since_last_build(&dependency_path, 
+                                                                dependency, 
+                                                                true,
+                                                                &record,
+                                                                    old_table,
+                                                                    &self.dirs) {
                             recompile.store(true, Ordering::Relaxed);
                             
                         }
                     }
+                    else {
+                        // dependency was deleted or moved so we need to recompile 
+                        // the file
+                        recompile.store(true, Ordering::Relaxed);
+                        
+                    }
                 });
                 return recompile.load(Ordering::Relaxed);
             }
 
             for dependency in dependencies {
-                if dependency != "\\" {
-                    let dependency_path = PathBuf::from(&dependency);
-                    if dependency_path.exists() {
-                        let source_metadata = dependency_path.metadata().expect("Failed to retrieve metadata from file");
-                        let duration = get_duration_since_modified(&source_metadata);
-                        if file_modified_since_last_build(&dependency_path, 
-                                                                &dependency, 
-                                                                true,
-                                                                duration,
-                                                                    old_table) {
-                            return true;
-                        }
-                    }
-                    else { 
+                let dependency_path = PathBuf::from(&dependency);
+                if dependency_path.exists() {
+                    let record = current_file_record(&dependency_path, old_table.get(&dependency));
+                    if file_modified_since_last_build(&dependency_path, 
+                                                            &dependency, 
+                                                            true,
+                                                            &record,
+                                                                old_table,
+                                                                &self.dirs) {
                         return true;
                     }
                 }
+                else { 
+                    return true;
+                }
             }
         }
         false
 
     }
 
+    /// Records the current contents of `source_file_path` and the fingerprint of its
+    /// `command`, for a source file that is recompiled without being compared to
+    /// its old record (e.g. because every source file has to be recompiled).
+    pub fn refresh_record(&mut self,
+                          source_file_path : &Path,
+                          command : u64,
+                          old_table : &HashMap<String, FileRecord>)
+    {
+        let source_file_name = source_file_path.to_str().unwrap();
+        let record = FileRecord {
+            command,
+            ..current_file_record(source_file_path, old_table.get(source_file_name))
+        };
+        self.table.insert(source_file_name.to_string(), record);
+        self.flags |= BuildTableFlags::RECORDS_REFRESHED;
+    }
+
     /// Removes `path_str` from the build table.
     #[inline]
     pub fn erase(&mut self, path_str : &str)
@@ -344,12 +515,12 @@ impl Drop for BuildTable
     /// I doubt this is a good idea....
     fn drop(&mut self)
     {
-        // No point of writing to file if none of the dependencies changed, and writing to file must be
-        // explicitly enabled
-        if self.flags&BuildTableFlags::ANY_DEPENDENCIES_CHANGED == BuildTableFlags::ANY_DEPENDENCIES_CHANGED {
-            let mut f = File::create(BUILD_TABLE_FILE).expect("Failed to create build table file");
+        // No point of writing to file if none of the dependencies changed (or no modification
+        // times were refreshed), and writing to file must be explicitly enabled
+        if self.flags&(BuildTableFlags::ANY_DEPENDENCIES_CHANGED|BuildTableFlags::RECORDS_REFRESHED) != BuildTableFlags::NONE {
+            let mut f = File::create(&self.dirs.table).expect("Failed to create build table file");
             for (k, v) in &self.table {
-                f.write_all(format!("{k}={v}\n").as_bytes()).expect("Failed to write to build table file");
+                f.write_all(format!("{k}={}:{}:{}\n", v.modified, v.hash, v.command).as_bytes()).expect("Failed to write to build table file");
             }
         }
     }
@@ -0,0 +1,139 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use bitflags::bitflags;
+use walkdir::WalkDir;
+
+use crate::{build::Build, buildtable::{BUILD_TABLE_DIRECTORY, BuildDirectories}, compiler, selected_profile};
+
+bitflags! {
+    /// The kinds of artifacts 'quikc clean' removes. Without any, everything
+    /// quikc created is removed.
+    pub struct CleanScope : u8 {
+        const NONE = 0;
+        const OBJECTS = 1 << 0;
+        const DEPS = 1 << 1;
+        const ASM = 1 << 2;
+        const BINARIES = 1 << 3;
+    }
+}
+
+/// Returns the build directories of the selected profile, or of every profile
+/// that was built if none was selected on the command line.
+fn profile_dirs(build_config : &Build) -> Vec<BuildDirectories>
+{
+    if selected_profile().is_some() {
+        return vec![build_config.dirs.clone()];
+    }
+    let Ok(entries) = fs::read_dir(BUILD_TABLE_DIRECTORY) else {
+        return Vec::new();
+    };
+    let mut profiles = entries.flatten()
+                              .filter(|entry| entry.path().is_dir())
+                              .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                              .collect::<Vec<String>>();
+    profiles.sort();
+    profiles.iter().map(|profile| BuildDirectories::new(profile)).collect()
+}
+
+/// Returns the output files of the targets, along with the symbolic links
+/// that point to the shared libraries.
+fn target_files(build_config : &Build) -> Vec<PathBuf>
+{
+    build_config.targets.iter()
+                        .flat_map(|target| [target.output_file()].into_iter().chain(target.shared_library_links()))
+                        .map(PathBuf::from)
+                        .collect()
+}
+
+/// Returns the files and directories that are removed for `scope`, in the
+/// profiles returned by `profile_dirs`. If `source_files` are given, only their
+/// object, dependency and assembly files are. Paths that do not exist are left out.
+pub fn artifacts(build_config : &Build, scope : CleanScope, source_files : &[String]) -> Result<Vec<PathBuf>, String>
+{
+    let mut paths = Vec::new();
+
+    if !source_files.is_empty() {
+        if scope.contains(CleanScope::BINARIES) {
+            return Err("'--bin' can not be used together with source files".to_string());
+        }
+        if let Some(file) = source_files.iter().find(|file| !compiler::is_c_source_file(file) && !compiler::is_cpp_source_file(file)) {
+            return Err(format!("'{file}' is not a C or C++ source file"));
+        }
+        let scope = if scope.is_empty() {CleanScope::OBJECTS|CleanScope::DEPS|CleanScope::ASM} else {scope};
+        for dirs in profile_dirs(build_config) {
+            for file in source_files.iter().map(Path::new) {
+                let outputs = [(CleanScope::OBJECTS, &dirs.obj, "o"), (CleanScope::DEPS, &dirs.deps, "d"), (CleanScope::ASM, &dirs.asm, "s")];
+                paths.extend(outputs.into_iter()
+                                    .filter(|(kind, _, _)| scope.contains(*kind))
+                                    .map(|(_, dir, ext)| PathBuf::from(compiler::to_output_file(file, dir, ext))));
+            }
+        }
+    }
+    // a profile without any scope is removed as a whole, and so is the build
+    // directory (including the toolchain caches) when nothing was selected at all
+    else if scope.is_empty() {
+        match selected_profile() {
+            Some(_) => paths.push(PathBuf::from(&build_config.dirs.root)),
+            None => {
+                paths.push(PathBuf::from(BUILD_TABLE_DIRECTORY));
+                paths.extend(target_files(build_config));
+            }
+        }
+    }
+    else {
+        for dirs in profile_dirs(build_config) {
+            let dirs = [(CleanScope::OBJECTS, dirs.obj), (CleanScope::DEPS, dirs.deps), (CleanScope::ASM, dirs.asm)];
+            paths.extend(dirs.into_iter()
+                             .filter(|(kind, _)| scope.contains(*kind))
+                             .map(|(_, dir)| PathBuf::from(dir)));
+        }
+        if scope.contains(CleanScope::BINARIES) {
+            paths.extend(target_files(build_config));
+        }
+    }
+
+    // symbolic links are checked themselves, since the file they point to may be gone already
+    paths.retain(|path| fs::symlink_metadata(path).is_ok());
+    Ok(paths)
+}
+
+/// Returns the number of files in `path` (a file or a directory) and their total size.
+pub fn disk_usage(path : &Path) -> (usize, u64)
+{
+    // a symbolic link is counted itself, not the file it points to
+    if let Some(metadata) = fs::symlink_metadata(path).ok().filter(|metadata| metadata.is_symlink()) {
+        return (1, metadata.len());
+    }
+    WalkDir::new(path).into_iter()
+                      .flatten()
+                      .filter(|entry| !entry.file_type().is_dir())
+                      .fold((0, 0), |(files, size), entry| {
+                          (files + 1, size + entry.metadata().map(|metadata| metadata.len()).unwrap_or_default())
+                      })
+}
+
+/// Removes `path`, which may be a file, a symbolic link or a directory.
+pub fn remove(path : &Path) -> Result<(), String>
+{
+    let result = match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(path),
+        _ => fs::remove_file(path)
+    };
+    result.map_err(|e| format!("failed to remove '{}': {e}", path.display()))
+}
+
+/// Returns `bytes` in the largest unit that keeps the value at least 1, e.g. '1.5 MiB'.
+pub fn human_readable_size(bytes : u64) -> String
+{
+    const UNITS : [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
@@ -0,0 +1,465 @@
+use crate::{QuikcFlags, clean::CleanScope, project::Template};
+
+/// The commands quikc can run. Without a command, the project is built.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Subcommand
+{
+    Build,
+    Check,
+    Asm,
+    Run,
+    Config,
+    CompileCommands,
+    Clean,
+    New,
+    Init
+}
+
+/// A command, as it is described in the help output.
+struct CommandDefinition
+{
+    command : Subcommand,
+    name : &'static str,
+    // the operands the command takes, e.g. '[FILES]...'
+    operands : Option<&'static str>,
+    about : &'static str
+}
+
+const COMMANDS : [CommandDefinition; 9] = [
+    CommandDefinition {
+        command : Subcommand::Build,
+        name : "build",
+        operands : None,
+        about : "Compile the source files and link the targets (default)"
+    },
+    CommandDefinition {
+        command : Subcommand::Check,
+        name : "check",
+        operands : None,
+        about : "Compile the source files without linking them"
+    },
+    CommandDefinition {
+        command : Subcommand::Asm,
+        name : "asm",
+        operands : Some("[FILES]..."),
+        about : "Write the assembly of the given source files (or every source file)"
+    },
+    CommandDefinition {
+        command : Subcommand::Run,
+        name : "run",
+        operands : Some("[-- ARGS]..."),
+        about : "Build the project and run the executable with ARGS"
+    },
+    CommandDefinition {
+        command : Subcommand::Config,
+        name : "config",
+        operands : None,
+        about : "Print the compilers and flags the project is built with"
+    },
+    CommandDefinition {
+        command : Subcommand::CompileCommands,
+        name : "compile-commands",
+        operands : None,
+        about : "Write 'compile_commands.json' without building"
+    },
+    CommandDefinition {
+        command : Subcommand::Clean,
+        name : "clean",
+        operands : Some("[FILES]..."),
+        about : "Remove the build artifacts (only the ones of the given source files, if any)"
+    },
+    CommandDefinition {
+        command : Subcommand::New,
+        name : "new",
+        operands : Some("<NAME>"),
+        about : "Create a project in a new directory called NAME"
+    },
+    CommandDefinition {
+        command : Subcommand::Init,
+        name : "init",
+        operands : Some("[NAME]"),
+        about : "Create a project in the current directory, named after it unless NAME is given"
+    }
+];
+
+/// An option, which is only accepted by the commands in `commands`.
+struct OptionDefinition
+{
+    long : &'static str,
+    short : Option<char>,
+    // the name of the option's value in the help output, if it takes one
+    value : Option<&'static str>,
+    about : &'static str,
+    commands : &'static [Subcommand]
+}
+
+// the commands that read the build configuration, the ones that compile and the ones that create a project
+const ALL_COMMANDS : &[Subcommand] = &[Subcommand::Build, Subcommand::Check, Subcommand::Asm, Subcommand::Run, Subcommand::Config, Subcommand::CompileCommands, Subcommand::Clean, Subcommand::New, Subcommand::Init];
+const PROJECT_COMMANDS : &[Subcommand] = &[Subcommand::Build, Subcommand::Check, Subcommand::Asm, Subcommand::Run, Subcommand::Config, Subcommand::CompileCommands, Subcommand::Clean];
+const COMPILING_COMMANDS : &[Subcommand] = &[Subcommand::Build, Subcommand::Check, Subcommand::Asm, Subcommand::Run];
+const CREATING_COMMANDS : &[Subcommand] = &[Subcommand::New, Subcommand::Init];
+const CLEAN_COMMAND : &[Subcommand] = &[Subcommand::Clean];
+const RUN_COMMAND : &[Subcommand] = &[Subcommand::Run];
+
+const OPTIONS : [OptionDefinition; 15] = [
+    OptionDefinition {
+        long : "release",
+        short : Some('r'),
+        value : None,
+        about : "Use the release profile",
+        commands : PROJECT_COMMANDS
+    },
+    OptionDefinition {
+        long : "profile",
+        short : Some('p'),
+        value : Some("NAME"),
+        about : "Use the profile called NAME",
+        commands : PROJECT_COMMANDS
+    },
+    OptionDefinition {
+        long : "jobs",
+        short : Some('j'),
+        value : Some("N"),
+        about : "Run at most N compiler jobs at once (default: one per core)",
+        commands : COMPILING_COMMANDS
+    },
+    OptionDefinition {
+        long : "target",
+        short : None,
+        value : Some("NAME"),
+        about : "Run the executable called NAME, if the project has several",
+        commands : RUN_COMMAND
+    },
+    OptionDefinition {
+        long : "template",
+        short : Some('t'),
+        value : Some("TEMPLATE"),
+        about : "Create the project from TEMPLATE: c (default), cpp, static, shared, embedded or embedded-cpp",
+        commands : CREATING_COMMANDS
+    },
+    OptionDefinition {
+        long : "git",
+        short : None,
+        value : None,
+        about : "Initialize a git repository, with a '.gitignore' for the build files",
+        commands : CREATING_COMMANDS
+    },
+    OptionDefinition {
+        long : "objects",
+        short : None,
+        value : None,
+        about : "Only remove the object files",
+        commands : CLEAN_COMMAND
+    },
+    OptionDefinition {
+        long : "deps",
+        short : None,
+        value : None,
+        about : "Only remove the dependency files",
+        commands : CLEAN_COMMAND
+    },
+    OptionDefinition {
+        long : "asm",
+        short : None,
+        value : None,
+        about : "Only remove the assembly files",
+        commands : CLEAN_COMMAND
+    },
+    OptionDefinition {
+        long : "bin",
+        short : None,
+        value : None,
+        about : "Only remove the executables and libraries",
+        commands : CLEAN_COMMAND
+    },
+    OptionDefinition {
+        long : "dry-run",
+        short : Some('n'),
+        value : None,
+        about : "List what would be removed without removing anything",
+        commands : CLEAN_COMMAND
+    },
+    OptionDefinition {
+        long : "quiet",
+        short : Some('q'),
+        value : None,
+        about : "Hide notes, given twice hides everything but errors and compiler output",
+        commands : ALL_COMMANDS
+    },
+    OptionDefinition {
+        long : "verbose",
+        short : None,
+        value : None,
+        about : "Print every command that is run",
+        commands : ALL_COMMANDS
+    },
+    OptionDefinition {
+        long : "help",
+        short : None,
+        value : None,
+        about : "Print help",
+        commands : ALL_COMMANDS
+    },
+    OptionDefinition {
+        long : "version",
+        short : Some('V'),
+        value : None,
+        about : "Print the version",
+        commands : ALL_COMMANDS
+    }
+];
+
+// short options of older versions of quikc, with what replaces them. They are
+// rejected instead of being reused, so that old scripts do not silently do something else
+const REMOVED_OPTIONS : [(char, &str); 4] = [
+    ('c', "'-c' was replaced by the 'check' command, run 'quikc check' instead"),
+    ('S', "'-S' was replaced by the 'asm' command, run 'quikc asm' instead"),
+    ('h', "'-h' was replaced by '-q' ('--quiet'), and '-hh' by '-qq'"),
+    ('v', "'-v' was replaced by '-V' ('--version')")
+];
+
+/// The parsed command line.
+#[derive(PartialEq, Eq, Debug)]
+pub struct Cli
+{
+    // None if no command was given
+    pub command : Option<Subcommand>,
+    pub flags : QuikcFlags,
+    pub profile : Option<String>,
+    pub jobs : Option<usize>,
+    // the executable 'quikc run' runs
+    pub target : Option<String>,
+    pub operands : Vec<String>,
+    pub template : Option<Template>,
+    pub git : bool,
+    pub clean_scope : CleanScope,
+    pub dry_run : bool,
+    pub show_help : bool,
+    pub show_version : bool
+}
+
+impl Cli
+{
+    /// Returns the command to run, which is 'build' if none was given.
+    #[inline]
+    pub fn command(&self) -> Subcommand
+    {
+        self.command.unwrap_or(Subcommand::Build)
+    }
+
+    /// Applies `option` (with its value, if it takes one) to the command line.
+    fn apply(&mut self, option : &OptionDefinition, value : Option<String>) -> Result<(), String>
+    {
+        match option.long {
+            "release" | "profile" => {
+                let profile = value.unwrap_or_else(|| "release".to_string());
+                if self.profile.is_some() {
+                    return Err("the profile can only be selected once, with either '--release' or '--profile'".to_string());
+                }
+                self.profile = Some(profile);
+            },
+            "jobs" => {
+                let value = value.unwrap_or_default();
+                match value.parse::<usize>() {
+                    Ok(jobs) if jobs > 0 => self.jobs = Some(jobs),
+                    _ => return Err(format!("invalid value '{value}' for '--jobs', expected a number greater than 0"))
+                }
+            },
+            "target" => self.target = value,
+            "template" => {
+                let value = value.unwrap_or_default();
+                let template = Template::from_name(&value).ok_or_else(|| {
+                    format!("invalid value '{value}' for '--template', expected one of: {}", Template::NAMES.join(", "))
+                })?;
+                self.template = Some(template);
+            },
+            "git" => self.git = true,
+            "objects" => self.clean_scope |= CleanScope::OBJECTS,
+            "deps" => self.clean_scope |= CleanScope::DEPS,
+            "asm" => self.clean_scope |= CleanScope::ASM,
+            "bin" => self.clean_scope |= CleanScope::BINARIES,
+            "dry-run" => self.dry_run = true,
+            "quiet" => {
+                // '-qq' hides all output, with the exception of errors and compiler/linker output
+                if self.flags&QuikcFlags::HIDE_VERBOSE_OUTPUT == QuikcFlags::HIDE_VERBOSE_OUTPUT {
+                    self.flags |= QuikcFlags::HIDE_OUTPUT;
+                }
+                self.flags |= QuikcFlags::HIDE_VERBOSE_OUTPUT;
+            },
+            "verbose" => self.flags |= QuikcFlags::VERBOSE,
+            "help" => self.show_help = true,
+            "version" => self.show_version = true,
+            _ => unreachable!("option '--{}' is not handled", option.long)
+        }
+        Ok(())
+    }
+}
+
+fn find_command(name : &str) -> Option<&'static CommandDefinition>
+{
+    COMMANDS.iter().find(|definition| definition.name == name)
+}
+
+fn command_definition(command : Subcommand) -> &'static CommandDefinition
+{
+    COMMANDS.iter().find(|definition| definition.command == command).unwrap()
+}
+
+/// Parses the command line arguments (without the program's name). Options can
+/// be given before or after the command, long options take their value either
+/// as '--name=value' or as the next argument, and short options can be grouped
+/// (e.g. '-qq' or '-rj4'). Everything after '--' is an operand.
+pub fn parse(args : &[String]) -> Result<Cli, String>
+{
+    let mut cli = Cli {
+        command : None,
+        flags : QuikcFlags::NONE,
+        profile : None,
+        jobs : None,
+        target : None,
+        operands : Vec::new(),
+        template : None,
+        git : false,
+        clean_scope : CleanScope::NONE,
+        dry_run : false,
+        show_help : false,
+        show_version : false
+    };
+    let mut given_options = Vec::new();
+    let mut only_operands = false;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        if only_operands || !arg.starts_with('-') || arg == "-" {
+            // 'quikc help <command>' is the same as 'quikc <command> --help'
+            if cli.command.is_none() && !only_operands && arg == "help" && !cli.show_help {
+                cli.show_help = true;
+                continue;
+            }
+            if cli.command.is_none() && !only_operands {
+                let definition = find_command(arg).ok_or_else(|| {
+                    let names = COMMANDS.iter().map(|definition| definition.name).collect::<Vec<&str>>();
+                    format!("unknown command '{arg}', the commands are: {}", names.join(", "))
+                })?;
+                cli.command = Some(definition.command);
+                continue;
+            }
+            let definition = command_definition(cli.command());
+            if definition.operands.is_none() {
+                return Err(format!("unexpected argument '{arg}', 'quikc {}' does not take any arguments", definition.name));
+            }
+            cli.operands.push(arg.to_owned());
+        }
+        else if arg == "--" {
+            only_operands = true;
+        }
+        else if let Some(long) = arg.strip_prefix("--") {
+            let (name, inline_value) = match long.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (long, None)
+            };
+            let option = OPTIONS.iter()
+                                .find(|option| option.long == name)
+                                .ok_or_else(|| format!("unknown option '--{name}'"))?;
+            let value = match (option.value, inline_value) {
+                (Some(_), Some(value)) => Some(value),
+                (Some(value_name), None) => Some(args.next().cloned().ok_or_else(|| format!("'--{name}' requires a value ({value_name})"))?),
+                (None, Some(_)) => return Err(format!("'--{name}' does not take a value")),
+                (None, None) => None
+            };
+            cli.apply(option, value)?;
+            given_options.push(option);
+        }
+        else {
+            let shorts = &arg[1..];
+            for (i, short) in shorts.char_indices() {
+                let option = OPTIONS.iter()
+                                    .find(|option| option.short == Some(short))
+                                    .ok_or_else(|| match REMOVED_OPTIONS.iter().find(|(removed, _)| *removed == short) {
+                                        Some((_, replacement)) => replacement.to_string(),
+                                        None if shorts.len() == short.len_utf8() => format!("unknown option '-{short}'"),
+                                        None => format!("unknown option '-{short}' in '{arg}'")
+                                    })?;
+                // an option that takes a value ends the group, e.g. '-j4'
+                if let Some(value_name) = option.value {
+                    let rest = &shorts[i + short.len_utf8()..];
+                    let value = match rest.is_empty() {
+                        true => args.next().cloned().ok_or_else(|| format!("'-{short}' requires a value ({value_name})"))?,
+                        false => rest.to_string()
+                    };
+                    cli.apply(option, Some(value))?;
+                    given_options.push(option);
+                    break;
+                }
+                cli.apply(option, None)?;
+                given_options.push(option);
+            }
+        }
+    }
+
+    // options may come before the command, so they can only be checked once it is known
+    let command = cli.command();
+    if let Some(option) = given_options.iter().find(|option| !option.commands.contains(&command)) {
+        return Err(format!("'--{}' is not an option of 'quikc {}'", option.long, command_definition(command).name));
+    }
+    // '<NAME>' has to be given, while '[NAME]' is optional. Only '...' takes several operands
+    let definition = command_definition(command);
+    if let Some(operands) = definition.operands.filter(|_| !cli.show_help) {
+        if operands.starts_with('<') && cli.operands.is_empty() {
+            return Err(format!("'quikc {}' requires an argument: {operands}", definition.name));
+        }
+        if !operands.ends_with("...") && cli.operands.len() > 1 {
+            return Err(format!("unexpected argument '{}', 'quikc {}' takes only one argument", cli.operands[1], definition.name));
+        }
+    }
+    if cli.flags&QuikcFlags::VERBOSE == QuikcFlags::VERBOSE && cli.flags&QuikcFlags::HIDE_VERBOSE_OUTPUT == QuikcFlags::HIDE_VERBOSE_OUTPUT {
+        return Err("'--verbose' and '--quiet' can not be used together".to_string());
+    }
+    Ok(cli)
+}
+
+/// Returns the usage of `option` as it is shown in the help output, e.g. '-j, --jobs <N>'.
+fn option_usage(option : &OptionDefinition) -> String
+{
+    let short = option.short.map(|short| format!("-{short}, ")).unwrap_or_else(|| "    ".to_string());
+    let value = option.value.map(|value| format!(" <{value}>")).unwrap_or_default();
+    format!("{short}--{}{value}", option.long)
+}
+
+/// Returns the help output, for `command` if one is given, or for quikc itself.
+/// Both are generated from the definitions the command line is parsed with.
+pub fn help(command : Option<Subcommand>) -> String
+{
+    let mut help = String::new();
+    let options = OPTIONS.iter()
+                         .filter(|option| command.iter().all(|command| option.commands.contains(command)))
+                         .collect::<Vec<&OptionDefinition>>();
+
+    match command {
+        Some(command) => {
+            let definition = command_definition(command);
+            help.push_str(&format!("{}\n\nUsage: quikc {} [OPTIONS]", definition.about, definition.name));
+            if let Some(operands) = definition.operands {
+                help.push_str(&format!(" {operands}"));
+            }
+            help.push('\n');
+        },
+        None => {
+            help.push_str("A build system for C and C++ projects\n\nUsage: quikc [COMMAND] [OPTIONS]\n\nCommands:\n");
+            let width = COMMANDS.iter().map(|definition| definition.name.len()).max().unwrap_or_default();
+            for definition in &COMMANDS {
+                help.push_str(&format!("  {:width$}  {}\n", definition.name, definition.about));
+            }
+        }
+    }
+
+    help.push_str("\nOptions:\n");
+    let width = options.iter().map(|option| option_usage(option).len()).max().unwrap_or_default();
+    for option in options {
+        help.push_str(&format!("  {:width$}  {}\n", option_usage(option), option.about));
+    }
+    if command.is_none() {
+        help.push_str("\nRun 'quikc help <COMMAND>' for the options of a command.\n");
+    }
+    help
+}
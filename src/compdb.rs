@@ -0,0 +1,66 @@
+use std::{fs, path::PathBuf};
+
+use crate::{build::Build, compiler};
+
+/// The compilation database read by clangd, clang-tidy and most IDEs. It is
+/// written to the project's root directory, where these tools look for it.
+pub const COMPILE_COMMANDS_FILE : &str = "./compile_commands.json";
+
+/// Returns `value` as a JSON string, with its quotes, backslashes and control
+/// characters escaped.
+pub fn json_string(value : &str) -> String
+{
+    let mut json = String::with_capacity(value.len() + 2);
+    json.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c)
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// Returns the compilation database for `source_files`. Every entry holds the
+/// complete command that compiles the file to its object file, as quikc runs it.
+pub fn compile_commands(build_config : &Build, source_files : &[PathBuf]) -> String
+{
+    let directory = std::env::current_dir().expect("Failed to get the current directory");
+    let directory = json_string(&directory.to_string_lossy());
+
+    let entries = source_files.iter()
+                              .map(|source_file| {
+                                  let file = source_file.to_str().unwrap();
+                                  let cmd = compiler::object_file_command(build_config, file);
+                                  let arguments = [cmd.get_program()].into_iter()
+                                                                     .chain(cmd.get_args())
+                                                                     .map(|arg| json_string(&arg.to_string_lossy()))
+                                                                     .collect::<Vec<String>>();
+                                  let output = compiler::to_output_file(source_file, &build_config.dirs.obj, "o");
+                                  format!("  {{\n    \"directory\": {},\n    \"arguments\": [{}],\n    \"file\": {},\n    \"output\": {}\n  }}",
+                                          directory, arguments.join(", "), json_string(file), json_string(&output))
+                              })
+                              .collect::<Vec<String>>();
+
+    if entries.is_empty() {
+        return "[]\n".to_string();
+    }
+    format!("[\n{}\n]\n", entries.join(",\n"))
+}
+
+/// Writes the compilation database for `source_files`. The file is only written
+/// if it changed, since the tools reading it reload it whenever it is written.
+pub fn write_compile_commands(build_config : &Build, source_files : &[PathBuf])
+{
+    let contents = compile_commands(build_config, source_files);
+    if fs::read_to_string(COMPILE_COMMANDS_FILE).is_ok_and(|old_contents| old_contents == contents) {
+        return;
+    }
+    fs::write(COMPILE_COMMANDS_FILE, contents).expect("Failed to write compilation database");
+}
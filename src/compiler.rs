@@ -1,4 +1,4 @@
-use std::{path::PathBuf, process::{Command}, io::ErrorKind};
+use std::{fs, path::{PathBuf, Component}, process::{Command}, io::ErrorKind};
 
 #[cfg(test)]
     use std::sync::atomic::AtomicBool;
@@ -13,9 +13,8 @@ use crate::flags;
 use crate::QuikcFlags;
 
 
-use crate::{buildtable::{BUILD_TABLE_OBJECT_FILE_DIRECTORY, BUILD_TABLE_DEPS_DIRECTORY}, build::{Build, Compiler}};
+use crate::build::{Build, Compiler};
 
-pub const INCLUDE_PATH_FLAG : &str = "-I./include";
 pub const INCLUDE_PATH : &str = "./include";
 
 #[inline]
@@ -25,14 +24,35 @@ pub fn is_header_file(file : &str) -> bool
 }
 
 
-/// This function changes the path's extension and directory to the ones
-/// passed as arguments.
+/// Returns the path of the file that `path` is compiled to in `directory`.
+/// The output files mirror the project's layout and keep the source file's
+/// extension, so that files with the same name in different directories (or
+/// with different extensions) do not overwrite each other. For example,
+/// './src/net/util.c' becomes '<directory>/src/net/util.c.o'.
 #[inline]
 pub fn to_output_file(path : &Path, directory : &str, ext : &str) -> String
 {
-    // path.file_prefix() is currently unstable so cant use that. file_stem should
-    // work for our use case though.
-    format!("{}/{}.{}", directory, path.file_stem().unwrap().to_str().unwrap(), ext)
+    format!("{}/{}.{}", directory, without_current_dir(path).to_str().unwrap(), ext)
+}
+
+/// Returns the path without any '.' components, e.g. './src/./main.c' becomes 'src/main.c'.
+#[inline]
+pub fn without_current_dir(path : &Path) -> PathBuf
+{
+    path.components()
+        .filter(|component| component != &Component::CurDir)
+        .collect()
+}
+
+/// Creates the directory the output file will be written to, since the
+/// compiler does not create it by itself.
+#[inline]
+pub fn create_output_directory(output_file : &str)
+{
+    let directory = Path::new(output_file).parent().unwrap();
+    if !directory.is_dir() {
+        fs::create_dir_all(directory).expect("Failed to create output directory");
+    }
 }
 
 #[inline]
@@ -47,54 +67,143 @@ pub fn is_c_source_file(file : &str) -> bool
     file.ends_with(".c")
 }
 
-// dont support msvc at the moment
-#[inline]
-pub fn is_gcc_or_clang(compiler_name : &str) -> bool
+/// Returns a command that runs `compiler`. The compiler may be preceded by
+/// a launcher or be followed by arguments, e.g. 'ccache gcc'.
+pub fn compiler_command(compiler : &str) -> Command
+{
+    let mut words = compiler.split_whitespace();
+    let mut cmd = Command::new(words.next().unwrap_or_default());
+    cmd.args(words);
+    cmd
+}
+
+/// Prints the command line of `cmd` before it is run, if '--verbose' was given.
+pub fn print_command(cmd : &Command)
+{
+    if flags()&QuikcFlags::VERBOSE == QuikcFlags::NONE {
+        return;
+    }
+    let command_line = [cmd.get_program()].into_iter()
+                                           .chain(cmd.get_args())
+                                           .map(|arg| arg.to_string_lossy())
+                                           .collect::<Vec<_>>();
+    println!("{}", command_line.join(" "));
+}
+
+
+/// Replaces the compiler's name in `compiler_name` with the one `rename` returns.
+/// Only the last word is renamed, since the ones before it run the compiler
+/// (e.g. 'ccache gcc'), and its directory and the prefix or suffix of a cross
+/// or versioned compiler (e.g. 'x86_64-linux-gnu-gcc-13') are kept.
+fn rename_compiler(compiler_name : &str, rename : impl Fn(&str) -> Option<String>) -> Option<String>
+{
+    let compiler_name = compiler_name.trim_end();
+    let word_start = compiler_name.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+    let file_start = compiler_name[word_start..].rfind('/').map_or(word_start, |i| word_start + i + 1);
+    rename(&compiler_name[file_start..]).map(|file_name| format!("{}{file_name}", &compiler_name[..file_start]))
+}
+
+/// Returns `file_name` with the last occurrence of `from` replaced by `to`.
+fn replace_last(file_name : &str, from : &str, to : &str) -> Option<String>
+{
+    file_name.rfind(from).map(|i| format!("{}{to}{}", &file_name[..i], &file_name[i + from.len()..]))
+}
+
+/// Returns true if `file_name` is the compiler called `name`, with or without
+/// the prefix of a cross compiler and a version suffix (e.g. 'cc' or 'arm-none-eabi-cc').
+fn is_compiler_named(file_name : &str, name : &str) -> bool
 {
-    matches!(compiler_name, "gcc"|"g++"|"clang"|"clang++")
+    file_name.split('-').any(|word| word == name)
 }
 
+/// Returns the C compiler of the same vendor as `compiler_name`, e.g. 'gcc' for 'g++'
+/// or 'x86_64-linux-gnu-gcc-13' for 'x86_64-linux-gnu-g++-13'. C compilers and unknown
+/// compilers are returned as is.
+pub fn c_counterpart(compiler_name : &str) -> String
+{
+    rename_compiler(compiler_name, |file_name| {
+        if file_name.contains("clang++") {
+            replace_last(file_name, "clang++", "clang")
+        }
+        else if file_name.contains("g++") {
+            replace_last(file_name, "g++", "gcc")
+        }
+        else if is_compiler_named(file_name, "c++") {
+            replace_last(file_name, "c++", "cc")
+        }
+        else {
+            None
+        }
+    }).unwrap_or_else(|| compiler_name.to_string())
+}
+
+/// Returns the C++ compiler of the same vendor as `compiler_name`, e.g. 'g++' for 'gcc'
+/// or 'ccache clang++-17' for 'ccache clang-17'. C++ compilers are returned as is, and
+/// None is returned for compilers quikc does not know the C++ compiler of.
+pub fn cpp_counterpart(compiler_name : &str) -> Option<String>
+{
+    rename_compiler(compiler_name, |file_name| {
+        if file_name.contains("clang++") || file_name.contains("g++") || is_compiler_named(file_name, "c++") {
+            Some(file_name.to_string())
+        }
+        else if file_name.contains("clang") {
+            replace_last(file_name, "clang", "clang++")
+        }
+        else if file_name.contains("gcc") {
+            replace_last(file_name, "gcc", "g++")
+        }
+        else if is_compiler_named(file_name, "cc") {
+            replace_last(file_name, "cc", "c++")
+        }
+        else {
+            None
+        }
+    })
+}
 
+/// Returns true if the default configuration is used for files of the given language,
+/// which is the case if neither 'args' nor the language's own arguments are given
+/// (or if they are appended to the default configuration).
 #[inline]
-pub fn use_default_compiler_configuration(compiler : &Compiler) -> bool
+pub fn use_default_compiler_configuration(compiler : &Compiler, is_c_source_file : bool) -> bool
 {
     if let Some(true) = compiler.append_args {
-        return compiler.args.is_some();
+        return compiler.args.is_some() || compiler.c_args.is_some() || compiler.cpp_args.is_some();
     }
-    compiler.args.is_none()
+    compiler.args.is_none() && compiler.language_args(is_c_source_file).is_none()
 }
 
 /// Selects a default compiler, should be called only if a compiler has not
 /// been specified in the 'build.toml' file. Available default compilers to
-/// choose from include: gcc, clang, g++, clang++
-pub fn select_default_compiler() -> &'static str
+/// choose from include: gcc and clang for C, g++ and clang++ for C++
+pub fn select_default_compiler(cpp : bool) -> &'static str
 {
-    if match Command::new("gcc").output() {
-        Ok(_) => true,
-        Err(e) => !matches!(e.kind(), ErrorKind::NotFound)
-    } { return "gcc" }
-
-    if match Command::new("clang").output() {
-        Ok(_) => true,
-        Err(e) => !matches!(e.kind(), ErrorKind::NotFound)
-    } { return "clang" }
-
-    if match Command::new("g++").output() {
-        Ok(_) => true,
-        Err(e) => !matches!(e.kind(), ErrorKind::NotFound)
-    } { return "g++" }
-
-    if match Command::new("clang++").output() {
-        Ok(_) => true,
-        Err(e) => !matches!(e.kind(), ErrorKind::NotFound)
-    } { return "clang++" }
+    let compilers = if cpp {["g++", "clang++"]} else {["gcc", "clang"]};
+    for compiler in compilers {
+        if match Command::new(compiler).output() {
+            Ok(_) => true,
+            Err(e) => !matches!(e.kind(), ErrorKind::NotFound)
+        } { return compiler }
+    }
 
     eprintln!("{}", cformat!("<bold><red>error</red>:</bold> Could not find a default compiler to use.
                             Please specify your own in the 'Build.toml' file"));
     std::process::exit(1);
 }
 
-/// Compiles the source files to object files, which are stored in '/buildinfo/obj'.
+/// Returns the command that compiles `file` to its object file in the profile's 'obj' directory.
+/// This is also used to fingerprint the file's compiler command line, so that it
+/// is only recompiled if its own command changed.
+pub fn object_file_command(build_info : &Build, file : &str) -> Command
+{
+    let out = to_output_file(Path::new(file), &build_info.dirs.obj, "o");
+    let mut cmd = build_info.execute_compiler_with_build_info(file);
+    cmd.args(build_info.include_flags())
+       .args([file, "-c", "-o", &out]);
+    cmd
+}
+
+/// Compiles the source files to object files, which are stored in the profile's 'obj' directory.
 /// Returns true if the compilation was successful. If the compiler encounters an
 /// error, then the program will print the compiler's error message and exit the
 /// program. This makes it questionable as to why the return type is even necessary.
@@ -113,14 +222,14 @@ pub fn compile_to_object_files(source_files : &Vec<String>,
         }
         
         let out_file_path = PathBuf::from(file);
-        let out = to_output_file(&out_file_path, BUILD_TABLE_OBJECT_FILE_DIRECTORY, "o");
-        let dep_name = to_output_file(&out_file_path, BUILD_TABLE_DEPS_DIRECTORY, "d");
-
-        // Generate the file's dependencies
-        Command::new(&build_info.compiler.compiler)
-                .args([INCLUDE_PATH_FLAG, file, "-MM", "-o", &dep_name])
-                .spawn()
-                .expect("Failed to generate dependencies");
+        let out = to_output_file(&out_file_path, &build_info.dirs.obj, "o");
+        let dep_name = to_output_file(&out_file_path, &build_info.dirs.deps, "d");
+        // The compiler writes the dependencies to a temporary file which is only moved
+        // in place once the object file was produced, so a dependency file never
+        // describes an object file from a different compilation
+        let tmp_dep_name = format!("{dep_name}.tmp");
+        create_output_directory(&out);
+        create_output_directory(&dep_name);
 
         // 'Include what you use' is currently a experimental feature, and not toggled by default 
         // since it can probably cause the program to not compile
@@ -128,7 +237,9 @@ pub fn compile_to_object_files(source_files : &Vec<String>,
         if build_info.misc.toggle_iwyu.unwrap_or(false) {
             let standard = build_info.get_standard(file);
             let iwyu_cmd = Command::new("include-what-you-use")
-                                          .args([standard, INCLUDE_PATH_FLAG, file])
+                                          .arg(standard)
+                                          .args(build_info.include_flags())
+                                          .arg(file)
                                           .stdout(Stdio::piped())
                                           .spawn()
                                           .expect("Failed to spawn 'include-what-you-use'");
@@ -140,13 +251,19 @@ pub fn compile_to_object_files(source_files : &Vec<String>,
                     .expect("Failed to spawn 'iwyu-fix-includes'");
         }
 
-        // Compile the file with the appropriate flags specified in the build
-        let output = build_info.execute_compiler_with_build_info(file)
-                                       .args([INCLUDE_PATH_FLAG, file, "-c", "-o", &out])
-                                       .output()
-                                       .expect("Failed to execute compiler");
+        // Compile the file with the appropriate flags specified in the build, generating
+        // the file's dependencies as a side effect. '-MMD' leaves out the headers in
+        // system include directories, so third-party headers need '-MD' to be tracked
+        let dependency_flag = if build_info.system_include_dirs().is_empty() {"-MMD"} else {"-MD"};
+        let mut cmd = object_file_command(build_info, file);
+        cmd.args([dependency_flag, "-MF", &tmp_dep_name]);
+        print_command(&cmd);
+        let output = cmd.output().expect("Failed to execute compiler");
         
-        if !output.status.success() {
+        if output.status.success() {
+            fs::rename(&tmp_dep_name, &dep_name).expect("Failed to write dependency file");
+        }
+        else {
             let s = String::from_utf8_lossy(&output.stderr);
             eprintln!("{}\n{}", s, cformat!("<bold><red>error</red>:</bold> Failed to compile '{}'\nTerminating program.", file));
 
@@ -154,7 +271,12 @@ pub fn compile_to_object_files(source_files : &Vec<String>,
             // the next time the program is run, it will know that an error occurred so it can
             // recompile it.
             if Path::new(&out).exists() {
-                std::fs::remove_file(&out).expect("Failed to remove object file from build directory");
+                fs::remove_file(&out).expect("Failed to remove object file from build directory");
+            }
+            for dep_file in [&dep_name, &tmp_dep_name] {
+                if Path::new(dep_file).exists() {
+                    fs::remove_file(dep_file).expect("Failed to remove dependency file from build directory");
+                }
             }
             #[cfg(not(test))]
                 std::process::exit(1);
@@ -1,10 +1,9 @@
 
 // Default build configuration settings
 
-pub const GCC_COMPILER_NONEXCLUSIVE_WARNINGS : [&str; 25] = [
+pub const GCC_COMPILER_NONEXCLUSIVE_WARNINGS : [&str; 24] = [
     "-Wall",
     "-Wextra",
-    "-Werror",
     "-Wpedantic",
     "-Wimplicit-fallthrough",
     "-Wdouble-promotion",
@@ -54,8 +53,7 @@ pub const GCC_COMPILER_CPP_EXCLUSIVE_WARNINGS : [&str; 13] = [
 ];
 
 // C/C++ warnings for clang (do not use this on gcc compilers)
-pub const CLANG_COMPILER_NONEXCLUSIVE_WARNINGS : [&str; 51] = [
-    "-Werror",
+pub const CLANG_COMPILER_NONEXCLUSIVE_WARNINGS : [&str; 50] = [
     "-Wpedantic",
     "-Wall",
     "-Wextra",
@@ -108,6 +106,37 @@ pub const CLANG_COMPILER_NONEXCLUSIVE_WARNINGS : [&str; 51] = [
     "-Warray-parameter"
 ];
 
+// Added on top of the default warnings for the "strict" warning level, on gcc
+pub const GCC_STRICT_WARNINGS : [&str; 5] = [
+    "-Wundef",
+    "-Wmissing-declarations",
+    "-Wredundant-decls",
+    "-Wswitch-enum",
+    "-Wsign-conversion"
+];
+
+// C exclusive warnings for the "strict" warning level, on gcc
+pub const GCC_STRICT_C_WARNINGS : [&str; 3] = [
+    "-Wstrict-prototypes",
+    "-Wmissing-prototypes",
+    "-Wold-style-definition"
+];
+
+// Added on top of the default warnings for the "strict" warning level, on clang
+pub const CLANG_STRICT_WARNINGS : [&str; 5] = [
+    "-Wundef",
+    "-Wmissing-prototypes",
+    "-Wmissing-variable-declarations",
+    "-Wswitch-enum",
+    "-Wcomma"
+];
+
+// The only warnings for the "relaxed" warning level, work on gcc and clang
+pub const GCC_AND_CLANG_RELAXED_WARNINGS : [&str; 2] = [
+    "-Wall",
+    "-Wextra"
+];
+
 // dialect options that are language agnostic, work on gcc and clang
 pub const GCC_AND_CLANG_DIALECT_OPTIONS : [&str; 1] = [
     "-fdiagnostics-color"
@@ -154,27 +183,19 @@ pub const GCC_STATIC_ANALYSIS_OPTIONS : [&str; 1] = [
     "-fanalyzer"
 ];
 
-pub const GCC_AND_CLANG_OPTIMIZATION_OPTIONS : [&str; 2] = [
-    "-O2",
-    "-flto"
-];
-
-// using this is not recommended as it can potentially cause
-// unintentional bugs or crashes with the program
-pub const GCC_AND_CLANG_ENHANCED_OPTIMIZATION_OPTIONS : [&str; 4] = [
-    "-Ofast",
-    "-march=native",
-    "-mtune=native",
-    "-flto"
-];
-
 #[allow(dead_code)]
 pub const GCC_PROFILING_OPTIONS : [&str; 1] = [
     "fprofile-use"
 ];
 
-// Only used for release builds
-pub const GCC_AND_CLANG_LINKER_OPTIONS : [&str; 2] = [
-    "-flto",
+// Only used for release builds, the optimization level and
+// link time optimization flags are added separately
+pub const GCC_AND_CLANG_LINKER_OPTIONS : [&str; 1] = [
     "-s",
 ];
+
+// Used for static libraries: replace existing members, create the
+// archive if needed and write a symbol index
+pub const AR_OPTIONS : [&str; 1] = [
+    "rcs"
+];
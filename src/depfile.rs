@@ -0,0 +1,121 @@
+use std::{iter::Peekable, str::Chars};
+
+/// A rule from a Makefile dependency file, such as the ones generated
+/// by gcc and clang with '-MMD', e.g. 'main.o: main.c include/hi.h'
+#[derive(PartialEq, Eq, Debug, Default)]
+pub struct Rule
+{
+    pub targets : Vec<String>,
+    pub prerequisites : Vec<String>
+}
+
+/// The state of the rule that is currently being parsed.
+#[derive(Default)]
+struct RuleParser
+{
+    rule : Rule,
+    word : String,
+    in_prerequisites : bool
+}
+
+impl RuleParser
+{
+    /// Adds the current word to the targets (or the prerequisites, if
+    /// the targets were already separated by a colon).
+    fn finish_word(&mut self)
+    {
+        if self.word.is_empty() {
+            return;
+        }
+        let word = std::mem::take(&mut self.word);
+        if self.in_prerequisites {
+            self.rule.prerequisites.push(word);
+        }
+        else {
+            self.rule.targets.push(word);
+        }
+    }
+
+    /// Adds the rule to `rules` if it has any targets and starts a new one.
+    fn finish_rule(&mut self, rules : &mut Vec<Rule>)
+    {
+        self.finish_word();
+        let parser = std::mem::take(self);
+        if !parser.rule.targets.is_empty() {
+            rules.push(parser.rule);
+        }
+    }
+}
+
+/// Returns true if the next character ends the line (or the file).
+fn at_end_of_line(chars : &mut Peekable<Chars>) -> bool
+{
+    matches!(chars.peek(), None|Some('\n')|Some('\r'))
+}
+
+/// Parses the contents of a Makefile dependency file. This handles line
+/// continuations, multiple rules (such as the phony targets generated by
+/// '-MP') and the escapes used by gcc and clang: spaces and '#' are escaped
+/// with a backslash (with any backslashes preceding them doubled) and '$' is
+/// written as '$$'.
+pub fn parse(contents : &str) -> Vec<Rule>
+{
+    let mut rules = Vec::new();
+    let mut parser = RuleParser::default();
+    let mut chars = contents.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                let mut backslashes = 1;
+                while chars.peek() == Some(&'\\') {
+                    chars.next();
+                    backslashes += 1;
+                }
+
+                match chars.peek() {
+                    Some(' '|'\t'|'#') => {
+                        parser.word.push_str(&"\\".repeat(backslashes / 2));
+                        // an odd number of backslashes escapes the character
+                        if backslashes % 2 == 1 {
+                            parser.word.push(chars.next().unwrap());
+                        }
+                    },
+                    Some('\n'|'\r') => {
+                        // line continuation
+                        parser.word.push_str(&"\\".repeat(backslashes - 1));
+                        parser.finish_word();
+                        if chars.next() == Some('\r') && chars.peek() == Some(&'\n') {
+                            chars.next();
+                        }
+                    },
+                    _ => parser.word.push_str(&"\\".repeat(backslashes))
+                }
+            },
+            '$' => {
+                if chars.peek() == Some(&'$') {
+                    chars.next();
+                }
+                parser.word.push('$');
+            },
+            // Only a colon followed by whitespace separates the targets, so that
+            // colons can still be used in file names
+            ':' if !parser.in_prerequisites && (at_end_of_line(&mut chars) || matches!(chars.peek(), Some(' '|'\t'))) => {
+                parser.finish_word();
+                parser.in_prerequisites = true;
+            },
+            ' '|'\t'|'\r' => parser.finish_word(),
+            '\n' => parser.finish_rule(&mut rules),
+            '#' => {
+                // comment, skip to the end of the line
+                while !at_end_of_line(&mut chars) {
+                    chars.next();
+                }
+            },
+            _ => parser.word.push(c)
+        }
+    }
+    parser.finish_rule(&mut rules);
+
+    rules
+}
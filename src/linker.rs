@@ -1,11 +1,12 @@
-use std::{fs};
+use std::{fs, path::{Path, PathBuf}, process::Command, collections::{HashSet, HashMap}};
 
 use color_print::{cformat, cprintln};
+use walkdir::WalkDir;
 
 use crate::QuikcFlags;
 use crate::flags;
 
-use crate::{buildtable::BUILD_TABLE_OBJECT_FILE_DIRECTORY, build::{Build, Linker}};
+use crate::{buildtable::{FileRecord, get_duration_since_modified, current_file_record, command_fingerprint, read_link_table, write_link_table}, build::{Build, Linker, Target, TargetKind}, compiler::{self, to_output_file}, archiver};
 
 #[inline]
 pub fn use_default_linker_configuration(linker : &Linker) -> bool
@@ -16,45 +17,178 @@ pub fn use_default_linker_configuration(linker : &Linker) -> bool
     linker.args.is_none()
 }
 
-/// Links the object files given in '/buildtable/obj' and produces
-/// an executable file if the linker returned no errors. If nothing
-/// went wrong, this function will return true. Otherwise, it will
-/// terminate the program. The bool return value is only used for
-/// testing purposes.
-pub fn link_files(build_config : &Build) -> bool
+/// Returns the object files in the profile's 'obj' directory (and its subdirectories) that should be linked
+/// into `target`. If the target lists its own sources, only those (and
+/// its entry point) are used. Otherwise, every object file is used except
+/// for the entry points of the other targets.
+pub fn target_object_files(build_config : &Build, target : &Target) -> Vec<String>
 {
+    if let Some(sources) = &target.sources {
+        return target.main.iter()
+                          .chain(sources.iter())
+                          .map(|file| to_output_file(Path::new(file), &build_config.dirs.obj, "o"))
+                          .collect();
+    }
+
+    let other_entry_points = build_config.targets.iter()
+                                                 .filter(|other| other.name != target.name)
+                                                 .filter_map(|other| other.main.as_ref())
+                                                 .map(|file| to_output_file(Path::new(file), &build_config.dirs.obj, "o"))
+                                                 .collect::<HashSet<_>>();
+
     let mut object_files = Vec::new();
-    let dir = fs::read_dir(BUILD_TABLE_OBJECT_FILE_DIRECTORY)
-                          .expect("Failed to read from object file directory");
 
-    for object_file in dir {
-        let object_file_path = object_file.unwrap().path();
+    // sorted, so that the link order (and with it the fingerprint of the link command) is deterministic
+    for object_file in WalkDir::new(&build_config.dirs.obj).sort_by_file_name().into_iter().flatten() {
+        let object_file_path = object_file.path();
 
         // only add object files to be linked (for extra precaution)
         // besides, some builds, depending on the compilation flags, may
         // store other files in the 'obj' directory
         // e.g., clang with 'ftime-trace'
-        if object_file_path.extension().unwrap() == "o" {
+        if object_file.file_type().is_file() && object_file_path.extension().is_some_and(|ext| ext == "o") {
             let object_file_path_str = object_file_path.to_str().unwrap().to_string();
-            object_files.push(object_file_path_str);
+            if !other_entry_points.contains(&object_file_path_str) {
+                object_files.push(object_file_path_str);
+            }
+        }
+    }
+    object_files
+}
+
+/// Writes the version script generated from the target's export list.
+/// Every symbol that is not exported becomes local to the shared library.
+/// The file is left untouched if its contents did not change, so that
+/// its modification time can be used to tell if the target is out of date.
+fn write_export_list(target : &Target, version_script : &str)
+{
+    let mut contents = String::from("{\n    global:\n");
+    for symbol in target.exports.iter().flatten() {
+        contents.push_str(&format!("        {symbol};\n"));
+    }
+    contents.push_str("    local:\n        *;\n};\n");
+
+    if fs::read_to_string(version_script).map_or(true, |old_contents| old_contents != contents) {
+        fs::write(version_script, contents).expect("Failed to write version script");
+    }
+}
+
+/// Returns the libraries passed to the linker that can be found on disk.
+/// Libraries given with '-l' are only searched for in the directories
+/// given with '-L', as system libraries rarely change.
+fn library_files(cmd : &Command) -> Vec<PathBuf>
+{
+    let args = cmd.get_args()
+                  .filter_map(|arg| arg.to_str())
+                  .collect::<Vec<&str>>();
+    let search_dirs = args.iter()
+                          .filter_map(|arg| arg.strip_prefix("-L"))
+                          .collect::<Vec<&str>>();
+    let mut libraries = Vec::new();
+
+    for arg in &args {
+        if let Some(name) = arg.strip_prefix("-l") {
+            // '-l:file' searches for the exact file name
+            let file_names = match name.strip_prefix(':') {
+                Some(file_name) => vec![file_name.to_string()],
+                None => vec![format!("lib{name}.so"), format!("lib{name}.a")]
+            };
+            let library = search_dirs.iter()
+                                     .flat_map(|dir| file_names.iter().map(move |file_name| Path::new(dir).join(file_name)))
+                                     .find(|path| path.is_file());
+            libraries.extend(library);
+        }
+        else if !arg.starts_with('-') && Path::new(arg).is_file() {
+            libraries.push(PathBuf::from(arg));
         }
     }
+    libraries
+}
+
+/// Returns true if the output file of `target` is newer than every object
+/// file and library it is built from, and the command that produced it is
+/// the same as `cmd`.
+pub fn target_up_to_date(target : &Target, object_files : &[String], cmd : &Command, link_table : &HashMap<String, FileRecord>) -> bool
+{
+    let output_file = target.output_file();
+    let record = match link_table.get(&output_file) {
+        Some(record) if record.command == command_fingerprint(cmd) => record,
+        _ => return false
+    };
+
+    let output_modified = match fs::metadata(&output_file) {
+        Ok(metadata) => get_duration_since_modified(&metadata),
+        Err(_) => return false
+    };
+
+    // every profile links to the same output file, so it has to be the one
+    // this profile produced and not the one of the profile that was built last
+    if current_file_record(Path::new(&output_file), Some(record)).hash != record.hash {
+        return false;
+    }
+
+    let version_script = target.version_script().map(PathBuf::from);
+    object_files.iter()
+                .map(PathBuf::from)
+                .chain(library_files(cmd))
+                .chain(version_script)
+                .all(|input| match fs::metadata(input) {
+                    Ok(metadata) => get_duration_since_modified(&metadata) <= output_modified,
+                    Err(_) => false
+                })
+}
+
+/// Creates the symbolic link chain of a versioned shared library, replacing
+/// any links that were left behind by a previous build. On platforms without
+/// symbolic links, the library is copied instead.
+fn create_shared_library_links(target : &Target)
+{
+    let mut link_target = target.output_file();
+    for link in target.shared_library_links() {
+        if fs::symlink_metadata(&link).is_ok() {
+            fs::remove_file(&link).expect("Failed to remove old shared library link");
+        }
+        #[cfg(unix)]
+            std::os::unix::fs::symlink(&link_target, &link).expect("Failed to create shared library link");
+        #[cfg(not(unix))]
+            fs::copy(&link_target, &link).expect("Failed to copy shared library");
+        link_target = link;
+    }
+}
+
+/// Returns the command that links `object_files` into the output file of `target`.
+pub fn link_command(build_config : &Build, target : &Target, object_files : &[String]) -> Command
+{
+    let mut cmd = build_config.execute_linker_with_build_info(target, object_files);
+    cmd.args(object_files.iter())
+       .arg("-o")
+       .arg(target.output_file());
+    cmd
+}
+
+/// Runs `cmd` to link `target` and produces an executable (or a shared
+/// library) if the linker returned no errors. If nothing went wrong, this
+/// function will return true. Otherwise, it will terminate the program.
+/// The bool return value is only used for testing purposes.
+pub fn link_target(target : &Target, mut cmd : Command) -> bool
+{
+    let output_file = target.output_file();
+    let output_kind = match target.kind() {
+        TargetKind::Shared => "shared library",
+        _ => "executable"
+    };
 
     if flags()&QuikcFlags::HIDE_OUTPUT == QuikcFlags::NONE {
-        cprintln!("<green><bold>Linking executable</bold> '{}'...</green>", build_config.package.name);
+        cprintln!("<green><bold>Linking {}</bold> '{}'...</green>", output_kind, output_file);
     }
 
-    let cmd = build_config.execute_linker_with_build_info()
-                                  .args(object_files.iter())
-                                  .arg("-o")
-                                  .arg(&build_config.package.name)
-                                  .output()
-                                  .expect("Failed to execute linker");
+    compiler::print_command(&cmd);
+    let cmd = cmd.output().expect("Failed to execute linker");
     
     if !cmd.status.success() {
         let err_output = String::from_utf8_lossy(&cmd.stderr);
         eprintln!("{}\n{}", 
-                    cformat!("<bold><red>error</red>:</bold> Failed to link executable '{}'", build_config.package.name), 
+                    cformat!("<bold><red>error</red>:</bold> Failed to link {} '{}'", output_kind, output_file), 
                     err_output);
 
         #[cfg(not(test))]
@@ -63,5 +197,52 @@ pub fn link_files(build_config : &Build) -> bool
             return false;
     }
 
+    if target.kind() == TargetKind::Shared {
+        create_shared_library_links(target);
+    }
+
     true
-}
\ No newline at end of file
+}
+
+/// Links every target in the build configuration that is out of date. Static
+/// libraries are handed to the archiver instead. Returns true if all of the
+/// targets were built successfully.
+pub fn link_files(build_config : &Build) -> bool
+{
+    let mut link_table = read_link_table(&build_config.dirs);
+
+    let success = build_config.targets.iter().all(|target| {
+        if target.exports.is_some() {
+            write_export_list(target, &target.version_script().unwrap());
+        }
+
+        let object_files = target_object_files(build_config, target);
+        let cmd = match target.kind() {
+            TargetKind::Executable|TargetKind::Shared => link_command(build_config, target, &object_files),
+            TargetKind::Static => archiver::archive_command(build_config, target, &object_files)
+        };
+
+        if target_up_to_date(target, &object_files, &cmd, &link_table) {
+            return true;
+        }
+
+        let fingerprint = command_fingerprint(&cmd);
+        let built = match target.kind() {
+            TargetKind::Executable|TargetKind::Shared => link_target(target, cmd),
+            TargetKind::Static => archiver::archive_files(target, cmd)
+        };
+
+        if built {
+            let output_file = target.output_file();
+            let record = FileRecord {
+                command : fingerprint,
+                ..current_file_record(Path::new(&output_file), None)
+            };
+            link_table.insert(output_file, record);
+        }
+        built
+    });
+
+    write_link_table(&build_config.dirs, &link_table);
+    success
+}
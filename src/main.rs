@@ -1,8 +1,9 @@
-use std::{path::Path, collections::HashMap};
+use std::{path::Path, collections::HashMap, process::Command};
 
 use assembler::assemble_files;
 use build::Build;
-use color_print::{cprintln, cformat};
+use cli::{Cli, Subcommand};
+use color_print::cprintln;
 use bitflags::bitflags;
 use once_cell::sync::OnceCell;
 
@@ -19,6 +20,13 @@ mod version;
 mod logger;
 mod example;
 mod assembler;
+mod archiver;
+mod depfile;
+mod toolchain;
+mod compdb;
+mod cli;
+mod clean;
+mod project;
 
 #[cfg(test)]
     mod test;
@@ -33,12 +41,13 @@ bitflags! {
         const HIDE_VERBOSE_OUTPUT = 1 << 0;
         const DO_NOT_LINK = 1 << 1;
         const HIDE_OUTPUT = 1 << 2;
-        const SHOW_VERSION = 1 << 3;
-        const ASSEMBLE = 1 << 4;
+        const ASSEMBLE = 1 << 3;
+        const VERBOSE = 1 << 4;
     }
 }
 
 static INSTANCE : OnceCell<QuikcFlags> = OnceCell::new();
+static PROFILE : OnceCell<String> = OnceCell::new();
 
 /// Retrieves the command line flags that were passed to the program.
 #[inline]
@@ -46,6 +55,13 @@ pub fn flags() -> QuikcFlags {
     *INSTANCE.get().unwrap()
 }
 
+/// Retrieves the build profile that was selected on the command line
+/// with '--profile <name>' (or '--release'), if any.
+#[inline]
+pub fn selected_profile() -> Option<&'static String> {
+    PROFILE.get()
+}
+
 #[cfg(test)]
 pub fn set_flags() {
     INSTANCE.set(QuikcFlags::NONE).unwrap();
@@ -53,138 +69,278 @@ pub fn set_flags() {
 
 fn main() 
 {
-    INSTANCE.set(parse_args()).unwrap();
+    let cli = parse_args();
+    INSTANCE.set(cli.flags).unwrap();
+    if let Some(profile) = &cli.profile {
+        PROFILE.set(profile.to_owned()).unwrap();
+    }
+    if let Some(jobs) = cli.jobs {
+        rayon::ThreadPoolBuilder::new().num_threads(jobs)
+                                       .build_global()
+                                       .expect("Failed to create the thread pool");
+    }
+
+    // the project does not exist yet, so there is no build configuration to read
+    if let Subcommand::New | Subcommand::Init = cli.command() {
+        create_project(&cli);
+        return;
+    }
+
+    // cleaning only needs the targets and the profile, so the toolchain is left alone
+    if cli.command() == Subcommand::Clean {
+        clean(&cli, &Build::load_targets());
+        return;
+    }
+
     let build_config = Build::new();
+    match cli.command() {
+        Subcommand::Config => show_config(&build_config),
+        Subcommand::CompileCommands => {
+            compdb::write_compile_commands(&build_config, &walker::find_source_files(&build_config));
+            if flags()&QuikcFlags::HIDE_OUTPUT == QuikcFlags::NONE {
+                cprintln!("<green><bold>Successfully wrote '{}'</bold></green>", compdb::COMPILE_COMMANDS_FILE);
+            }
+        },
+        Subcommand::Asm => assemble(&cli.operands, &build_config),
+        Subcommand::Build | Subcommand::Check => build(&build_config),
+        Subcommand::Run => run(&cli, &build_config),
+        Subcommand::New | Subcommand::Init | Subcommand::Clean => unreachable!()
+    }
+}
+
+/// Compiles the source files that changed since the last build and, unless
+/// only checking, links the targets that are out of date.
+fn build(build_config : &Build)
+{
     let mut old_table = HashMap::new();
-    let mut build_table = buildtable::BuildTable::new(&mut old_table);
+    let mut build_table = buildtable::BuildTable::new(build_config, &mut old_table);
 
-    let source_files = walker::retrieve_source_files(SOURCE_DIRECTORY, 
-                                                                    &mut build_table,
-                                                                    &old_table);
+    let source_files = walker::retrieve_source_files(build_config,
+                                                     &mut build_table,
+                                                     &old_table);
+    // the database lists every source file, not only the ones that are recompiled
+    if build_config.misc.compile_commands.unwrap_or(false) {
+        compdb::write_compile_commands(build_config, &walker::find_source_files(build_config));
+    }
     if !source_files.is_empty() {
         // The return value does not matter to us as the program will terminate if an
         // error does occur.
-        compiler::compile_to_object_files(&source_files, &build_config);
+        compiler::compile_to_object_files(&source_files, build_config);
+    }
 
-        if flags()&QuikcFlags::DO_NOT_LINK == QuikcFlags::NONE {
-            return;
-        }
-        success(&build_config);
-        return;
+    // Only the targets that are out of date are relinked
+    if flags()&QuikcFlags::DO_NOT_LINK == QuikcFlags::NONE {
+        linker::link_files(build_config);
     }
-    // Check if the binary exists, if not we need to relink
-    if !Path::new(&build_config.package.name).is_file() && flags()&QuikcFlags::DO_NOT_LINK == QuikcFlags::NONE {
-        linker::link_files(&build_config);
+    success(build_config);
+}
+
+/// Builds the project and runs the executable with the arguments given after '--',
+/// in the working directory and with the environment variables from the '[run]'
+/// section. quikc exits with the executable's exit status.
+fn run(cli : &Cli, build_config : &Build)
+{
+    let target = build_config.executable_to_run(cli.target.as_deref()).unwrap_or_else(|e| {
+        error(&e);
+        std::process::exit(1);
+    });
+    build(build_config);
+
+    // the path is absolute, since the executable may run in another directory
+    let output_file = target.output_file();
+    let mut cmd = Command::new(std::env::current_dir().expect("Failed to get the current directory").join(&output_file));
+    cmd.args(&cli.operands);
+    if let Some(working_dir) = &build_config.run.working_dir {
+        if !Path::new(working_dir).is_dir() {
+            error(&format!("the working directory '{working_dir}' in '[run]' does not exist"));
+            std::process::exit(1);
+        }
+        cmd.current_dir(working_dir);
     }
-    success(&build_config);
+    cmd.envs(build_config.run.env.iter().flatten());
 
+    if flags()&QuikcFlags::HIDE_OUTPUT == QuikcFlags::NONE {
+        cprintln!("<green><bold>Running</bold> '{}'...</green>", output_file);
+    }
+    compiler::print_command(&cmd);
+    let status = cmd.status().unwrap_or_else(|e| {
+        error(&format!("failed to run '{output_file}': {e}"));
+        std::process::exit(1);
+    });
+    // like a shell, a signal that terminated the executable is reported as 128 + the signal
+    #[cfg(unix)]
+        let code = {
+            use std::os::unix::process::ExitStatusExt;
+            status.code().unwrap_or_else(|| 128 + status.signal().unwrap_or_default())
+        };
+    #[cfg(not(unix))]
+        let code = status.code().unwrap_or(1);
+    std::process::exit(code);
 }
 
-/// Parses the command line arguments passed to the program and
-/// returns a flag. Note that we can inline this since this is
-/// only called once in the beginning of the program.
-#[inline]
-fn parse_args() -> QuikcFlags
+/// Writes the assembly of `files` (or of every source file, if none are given).
+fn assemble(files : &[String], build_config : &Build)
 {
-    let args = std::env::args().collect::<Vec<String>>();
-    let mut files_to_assemble = Vec::new();
-    let mut flags = QuikcFlags::NONE;
-
-    for arg in &args {
-        let mut starts_flag = false;
-        for c in arg.chars() {
-            if c == '-' && !starts_flag {
-                starts_flag = true;
-                continue;
-            }
-            else if !starts_flag && flags&QuikcFlags::ASSEMBLE == QuikcFlags::NONE {
-                break;
-            }
-            if starts_flag {
-                match c {
-                    // show version 
-                    'v' => {
-                        // we don't want to show it more than once
-                        if flags&QuikcFlags::SHOW_VERSION == QuikcFlags::NONE {
-                            flags |= QuikcFlags::SHOW_VERSION;
-                            #[cfg(feature = "quikc-nightly")]
-                                println!("quikc-nightly v{}", version::NIGHTLY_VERSION);
-                            #[cfg(not(feature = "quikc-nightly"))]
-                                println!("quikc v{}", version::VERSIONS[0]);
-                        }
-                    },
-                    // hide verbose output
-                    'h' => {
-                        // if '-hh' is specified, then do not show any output at all, with the exception of errors
-                        // and compiler/linker output
-                        if flags&QuikcFlags::HIDE_VERBOSE_OUTPUT == QuikcFlags::HIDE_VERBOSE_OUTPUT {
-                            flags |= QuikcFlags::HIDE_OUTPUT;
-                        }
-                        else {
-                            flags |= QuikcFlags::HIDE_VERBOSE_OUTPUT
-                        }
-                    },
-                    // do not link after compiling
-                    'c' => flags |= QuikcFlags::DO_NOT_LINK,
-                    // run assembler on the source files, do not compile or link
-                    'S' => {
-                        flags |= QuikcFlags::ASSEMBLE;
-                        continue;
-                    },
-                    // unknown flag, print error and exit
-                    _ => {
-                        error("unknown option specified");
-                        std::process::exit(1);
-                    }
-                };
-            }
-            // If this is not a flag, and the assembler flag is set, then we can assume
-            // this is a file that the user wants an assembly output of
-            else if flags&QuikcFlags::ASSEMBLE == QuikcFlags::ASSEMBLE {
-                if !Path::new(arg).is_file() {
-                    eprintln!("{}", cformat!("<bold><red>error</red>:</bold> failed to disassemble '{}'. File does not exist", arg));
-                    std::process::exit(1);
-                }
-                files_to_assemble.push(arg);
-                break;
-            }
+    // NOTE: the build table is not created, so the recorded file times are left
+    // untouched. Only downside to this is that the program will reassemble the files
+    // every single time, but running assembler is not that frequent.
+    // If we did write the table, then we would have to sync the assembly/compiled
+    // states (or make some other compromise), which is annoying and more bug-prone.
+    for file in files {
+        if !Path::new(file).is_file() {
+            error(&format!("failed to assemble '{file}', the file does not exist"));
+            std::process::exit(1);
         }
     }
+    let files_to_assemble = files.iter().collect::<Vec<&String>>();
+    assemble_files(&files_to_assemble, build_config);
 
-    // user just wanted to check the version, exit here.
-    if flags == QuikcFlags::SHOW_VERSION {
-        std::process::exit(0);
+    let build_type = &build_config.active_profile.name;
+    if flags()&QuikcFlags::HIDE_OUTPUT == QuikcFlags::NONE {
+        if files.len() == 1 {
+            cprintln!("<green><bold>Successfully assembled source file: '{}' [{}]</bold></green>", files[0], build_type);
+        }
+        else {
+            cprintln!("<green><bold>Successfully assembled source files [{}]</bold></green>", build_type);
+        }
     }
+}
 
-    if flags&QuikcFlags::ASSEMBLE == QuikcFlags::ASSEMBLE {
-        // NOTE: BuildTable::drop() won't be called because we will
-        // exit the program before it can write to the file. This is useful
-        // because we don't want to modify any of the file times. Only
-        // downside to this is that the program will reassemble the files
-        // every single time, but running assembler is not that frequent.
-        // If we do call drop(), then we have to sync the assembly/compiled
-        // states (or make some other compromise), which is annoying and more bug-prone.
-        INSTANCE.set(flags).unwrap();
-        let build = Build::new();
-        assemble_files(&files_to_assemble, &build);
-
-        let build_type = match build.package.debug_build {
-            true => "debug",
-            false => "release"
-        };
+/// Removes the build artifacts selected on the command line and reports how
+/// much disk space was reclaimed. With '--dry-run', they are only listed.
+fn clean(cli : &Cli, build_config : &Build)
+{
+    let paths = clean::artifacts(build_config, cli.clean_scope, &cli.operands).unwrap_or_else(|e| {
+        error(&e);
+        std::process::exit(1);
+    });
 
-        if flags&QuikcFlags::HIDE_OUTPUT == QuikcFlags::NONE {
-            if files_to_assemble.len() == 1 {
-                cprintln!("<green><bold>Successfully assembled source file: '{}' [{}]</bold></green>", files_to_assemble[0], build_type);
-            }
-            else {
-                cprintln!("<green><bold>Successfully assembled source files [{}]</bold></green>", build_type);
+    let (mut total_files, mut total_size) = (0, 0);
+    for path in &paths {
+        let (files, size) = clean::disk_usage(path);
+        let readable_size = clean::human_readable_size(size);
+        if cli.dry_run {
+            println!("{} ({readable_size})", path.display());
+        }
+        else {
+            if flags()&QuikcFlags::VERBOSE == QuikcFlags::VERBOSE {
+                cprintln!("<bold>Removing</bold> '{}' ({})", path.display(), readable_size);
             }
+            clean::remove(path).unwrap_or_else(|e| {
+                error(&e);
+                std::process::exit(1);
+            });
         }
+        total_files += files;
+        total_size += size;
+    }
+
+    if flags()&QuikcFlags::HIDE_OUTPUT == QuikcFlags::HIDE_OUTPUT {
+        return;
+    }
+    let total_size = clean::human_readable_size(total_size);
+    match (cli.dry_run, total_files == 1) {
+        (true, true) => cprintln!("<bold>Would remove 1 file, reclaiming {}</bold>", total_size),
+        (true, false) => cprintln!("<bold>Would remove {} files, reclaiming {}</bold>", total_files, total_size),
+        (false, true) => cprintln!("<green><bold>Removed 1 file, reclaimed {}</bold></green>", total_size),
+        (false, false) => cprintln!("<green><bold>Removed {} files, reclaimed {}</bold></green>", total_files, total_size)
+    }
+}
+
+/// Creates a project from the template selected on the command line, in a new
+/// directory for 'quikc new' and in the current directory for 'quikc init'.
+fn create_project(cli : &Cli)
+{
+    let template = cli.template.unwrap_or_default();
+    let name = cli.operands.first().map(String::as_str);
+    let result = match cli.command() {
+        Subcommand::New => project::new_project(name.unwrap(), template, cli.git).map(|_| name.unwrap().to_string()),
+        _ => project::init_project(name, template, cli.git)
+    };
+    let name = result.unwrap_or_else(|e| {
+        error(&format!("failed to create the project: {e}"));
+        std::process::exit(1);
+    });
+
+    if flags()&QuikcFlags::HIDE_OUTPUT == QuikcFlags::NONE {
+        cprintln!("<green><bold>Successfully created {} '{}'</bold></green>", template.description(), name);
+    }
+}
+
+/// Parses the command line arguments passed to the program. Prints the help
+/// or the version and exits if either was asked for, and terminates the program
+/// if the arguments are invalid.
+fn parse_args() -> Cli
+{
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    let mut cli = cli::parse(&args).unwrap_or_else(|e| {
+        error(&format!("{e}\nRun 'quikc --help' for the available commands and options."));
+        std::process::exit(1);
+    });
+
+    if cli.show_help {
+        print!("{}", cli::help(cli.command));
         std::process::exit(0);
     }
+    if cli.show_version {
+        #[cfg(feature = "quikc-nightly")]
+            println!("quikc-nightly v{}", version::NIGHTLY_VERSION);
+        #[cfg(not(feature = "quikc-nightly"))]
+            println!("quikc v{}", version::VERSIONS[0]);
+        std::process::exit(0);
+    }
+
+    match cli.command() {
+        Subcommand::Check => cli.flags |= QuikcFlags::DO_NOT_LINK,
+        Subcommand::Asm => cli.flags |= QuikcFlags::ASSEMBLE,
+        _ => ()
+    }
+    cli
+}
+
+/// Prints the toolchain and the flags the project is built with, after the
+/// environment variables were applied to the build configuration.
+fn show_config(build_config : &Build)
+{
+    let describe_compiler = |compiler : &str, variable : &str, from_env : bool| {
+        let info = build_config.compilers.get(compiler).cloned().unwrap_or_default();
+        let mut description = format!("{compiler} ({}", info.family.name());
+        if !info.version.is_empty() {
+            description.push_str(&format!(" {}", info.version));
+        }
+        if from_env {
+            description.push_str(&format!(", from {variable}"));
+        }
+        description + ")"
+    };
+    let args = |cmd : std::process::Command| cmd.get_args()
+                                                .map(|arg| arg.to_string_lossy().into_owned())
+                                                .collect::<Vec<_>>()
+                                                .join(" ");
+
+    let c_compiler = describe_compiler(build_config.c_compiler(), "CC", build_config.environment.cc.is_some());
+    let cpp_compiler = describe_compiler(build_config.cpp_compiler(), "CXX", build_config.environment.cxx.is_some());
+    let c_flags = args(build_config.execute_compiler_with_build_info("main.c"));
+    let cpp_flags = args(build_config.execute_compiler_with_build_info("main.cpp"));
 
-    flags
+    cprintln!("<bold>profile:</bold> {}", build_config.active_profile.name);
+    cprintln!("<bold>C compiler:</bold> {}", c_compiler);
+    cprintln!("<bold>C++ compiler:</bold> {}", cpp_compiler);
+    cprintln!("<bold>C flags:</bold> {}", c_flags);
+    cprintln!("<bold>C++ flags:</bold> {}", cpp_flags);
+    for target in &build_config.targets {
+        // the link driver depends on the objects, which are the ones of the source files if nothing was built yet
+        let mut object_files = linker::target_object_files(build_config, target);
+        if object_files.is_empty() {
+            object_files = walker::find_source_files(build_config).iter()
+                                                                   .map(|file| compiler::to_output_file(file, &build_config.dirs.obj, "o"))
+                                                                   .collect();
+        }
+        let linker = build_config.execute_linker_with_build_info(target, &object_files);
+        let link_driver = linker.get_program().to_string_lossy().into_owned();
+        let link_flags = args(linker);
+        cprintln!("<bold>link flags ({}, with {}):</bold> {}", target.name, link_driver, link_flags);
+    }
 }
 
 /// Should be called when the program has successfully compiled
@@ -199,10 +355,7 @@ fn success(build_config : &Build)
         return;
     }
 
-    let build_type = match build_config.package.debug_build {
-        true => "debug",
-        false => "release"
-    };
+    let build_type = &build_config.active_profile.name;
 
     if flags()&QuikcFlags::DO_NOT_LINK == QuikcFlags::DO_NOT_LINK {
         cprintln!("<green><bold>Successfully compiled source files to object files [{}]</bold></green>", 
@@ -210,7 +363,18 @@ fn success(build_config : &Build)
         return;
     }
 
-    cprintln!("<green><bold>Successfully built target {} [{} build]</bold></green>", 
-                    build_config.package.name,
+    let target_names = build_config.targets.iter()
+                                           .map(|target| target.name.as_str())
+                                           .collect::<Vec<&str>>();
+
+    if target_names.len() == 1 {
+        cprintln!("<green><bold>Successfully built target {} [{} build]</bold></green>", 
+                        target_names[0],
+                        build_type);
+        return;
+    }
+
+    cprintln!("<green><bold>Successfully built targets {} [{} build]</bold></green>", 
+                    target_names.join(", "),
                     build_type);
 }
@@ -0,0 +1,230 @@
+use std::{fs, path::Path, process::Command};
+
+use const_format::concatcp;
+
+use crate::{build::{BUILD_CONFIG_FILE, EXPORT_MACRO}, buildtable::BUILD_TABLE_DIRECTORY, compiler::{self, INCLUDE_PATH}, SOURCE_DIRECTORY};
+
+/// The kinds of projects 'quikc new' and 'quikc init' can create.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Template
+{
+    #[default]
+    C,
+    Cpp,
+    Static,
+    Shared,
+    Embedded,
+    EmbeddedCpp
+}
+
+impl Template
+{
+    pub const NAMES : [&'static str; 6] = ["c", "cpp", "static", "shared", "embedded", "embedded-cpp"];
+
+    pub fn from_name(name : &str) -> Option<Template>
+    {
+        match name {
+            "c" => Some(Template::C),
+            "cpp" => Some(Template::Cpp),
+            "static" => Some(Template::Static),
+            "shared" => Some(Template::Shared),
+            "embedded" => Some(Template::Embedded),
+            "embedded-cpp" => Some(Template::EmbeddedCpp),
+            _ => None
+        }
+    }
+
+    /// Returns what the template creates, as it is shown once the project was created.
+    pub fn description(self) -> &'static str
+    {
+        match self {
+            Template::C => "C application",
+            Template::Cpp => "C++ application",
+            Template::Static => "static library",
+            Template::Shared => "shared library",
+            Template::Embedded => "embedded application",
+            Template::EmbeddedCpp => "embedded C++ application"
+        }
+    }
+
+    /// Returns the file the target is linked to, relative to the project's directory.
+    fn output_file(self, name : &str) -> String
+    {
+        match self {
+            Template::Static => format!("lib{name}.a"),
+            Template::Shared => format!("lib{name}.so*"),
+            _ => name.to_string()
+        }
+    }
+}
+
+/// Returns an error if `name` can not be used as the name of a package. Since
+/// it names the source file and the target, only letters, digits, '_' and '-'
+/// are allowed.
+pub fn validate_package_name(name : &str) -> Result<(), String>
+{
+    if name.is_empty() || name.starts_with('-') || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        return Err(format!("'{name}' is not a valid package name, it may only contain letters, digits, '_' and '-' (and may not start with '-')"));
+    }
+    Ok(())
+}
+
+/// Returns `name` as a C identifier, which prefixes the functions of the library templates.
+fn identifier(name : &str) -> String
+{
+    let identifier = name.replace('-', "_");
+    match identifier.starts_with(|c : char| c.is_ascii_digit()) {
+        true => format!("_{identifier}"),
+        false => identifier
+    }
+}
+
+fn build_config(name : &str, template : Template) -> String
+{
+    let compiler = if matches!(template, Template::Cpp | Template::EmbeddedCpp) {"g++"} else {"gcc"};
+    let mut config = format!("[package]\n    name = \"{name}\"\n    debug_build = true\n");
+
+    match template {
+        Template::Static => config.push_str("    kind = \"static\"\n"),
+        Template::Shared => config.push_str(&format!("    kind = \"shared\"\n    version = \"0.1.0\"\n    exports = [\"{}_add\"]\n", identifier(name))),
+        _ => ()
+    }
+    config.push_str(&format!("\n[compiler]\n    compiler = \"{compiler}\"\n"));
+
+    // freestanding code can not use the C (or C++) library, the startup files
+    // or the default libraries, and is not position independent
+    if matches!(template, Template::Embedded | Template::EmbeddedCpp) {
+        config.push_str("    args = [\n        \"-nolibc\",\n        \"-nostdlib\",\n        \"-nostartfiles\",\n        \"-nodefaultlibs\",\n        \
+                         \"-nostdinc\",\n        \"-fno-builtin\",\n        \"-fno-stack-protector\",\n        \"-fno-PIC\",\n        \"-fno-lto\"\n    ]\n");
+        if template == Template::EmbeddedCpp {
+            config.push_str("    cpp_args = [\n        \"-nostdinc++\"\n    ]\n");
+        }
+        config.push_str("    append_args = true\n\n[linker]\n    args = [\n        \"-fno-lto\"\n    ]\n    append_args = true\n");
+    }
+    config
+}
+
+/// Returns the source files the template starts with, relative to the project's directory.
+fn source_files(name : &str, template : Template) -> Vec<(String, String)>
+{
+    let identifier = identifier(name);
+    let library_header = format!("{INCLUDE_PATH}/{name}.h");
+    let library_source = format!("{SOURCE_DIRECTORY}/{name}.c");
+    let guard = format!("{}_H", identifier.to_ascii_uppercase());
+
+    match template {
+        Template::C => vec![(format!("{SOURCE_DIRECTORY}/{name}.c"),
+                             "#include <stdio.h>\n\nint main(void)\n{\n    printf(\"Hello world!\\n\");\n    return 0;\n}\n".to_string())],
+        Template::Cpp => vec![(format!("{SOURCE_DIRECTORY}/{name}.cc"),
+                               "#include <cstdio>\n\nint main()\n{\n    printf(\"Hello world!\\n\");\n    return 0;\n}\n".to_string())],
+        Template::Static | Template::Shared => vec![
+            // the exported function is declared with the macro quikc defines for shared libraries
+            (library_header, format!("#ifndef {guard}\n#define {guard}\n\n{}int {identifier}_add(int a, int b);\n\n#endif\n",
+                                     if template == Template::Shared {concatcp!(EXPORT_MACRO, " ")} else {""})),
+            (library_source, format!("#include \"{name}.h\"\n\nint {identifier}_add(int a, int b)\n{{\n    return a + b;\n}}\n"))
+        ],
+        // there is no entry point every target agrees on, so it is left to the user
+        Template::Embedded | Template::EmbeddedCpp => Vec::new()
+    }
+}
+
+/// Adds the build directory, the compilation database and the target to the
+/// '.gitignore' in `dir`, without touching the patterns that are already in it.
+fn write_gitignore(dir : &Path, name : &str, template : Template) -> Result<(), String>
+{
+    let path = dir.join(".gitignore");
+    let mut contents = fs::read_to_string(&path).unwrap_or_default();
+    let patterns = [format!("/{}/", BUILD_TABLE_DIRECTORY.trim_start_matches("./")),
+                    "/compile_commands.json".to_string(),
+                    format!("/{}", template.output_file(name))];
+
+    for pattern in patterns {
+        if contents.lines().any(|line| line.trim() == pattern) {
+            continue;
+        }
+        if !contents.is_empty() && !contents.ends_with('\n') {
+            contents.push('\n');
+        }
+        contents.push_str(&pattern);
+        contents.push('\n');
+    }
+    fs::write(&path, contents).map_err(|e| format!("failed to write '{}': {e}", path.display()))
+}
+
+/// Initializes a git repository in `dir`, unless it already has one.
+fn init_git_repository(dir : &Path) -> Result<(), String>
+{
+    if dir.join(".git").exists() {
+        return Ok(());
+    }
+    let mut cmd = Command::new("git");
+    cmd.arg("init").arg("--quiet").arg(dir);
+    compiler::print_command(&cmd);
+    match cmd.status() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(_) => Err(format!("failed to initialize a git repository in '{}'", dir.display())),
+        Err(e) => Err(format!("failed to run git: {e}"))
+    }
+}
+
+/// Creates the project called `name` from `template` in `dir`, which is created
+/// if it does not exist. A directory that already holds a 'Build.toml' is never
+/// touched, and existing source files are kept as they are.
+pub fn create_project(dir : &Path, name : &str, template : Template, git : bool) -> Result<(), String>
+{
+    validate_package_name(name)?;
+
+    // the paths are relative to the project's directory, without their leading './'
+    let path = |file : &str| dir.join(file.trim_start_matches("./"));
+    let build_config_file = path(BUILD_CONFIG_FILE);
+    if build_config_file.exists() {
+        return Err(format!("'{}' already exists, refusing to overwrite an existing project", build_config_file.display()));
+    }
+
+    let create_dir = |path : &Path| fs::create_dir_all(path).map_err(|e| format!("failed to create directory '{}': {e}", path.display()));
+    create_dir(dir)?;
+    create_dir(&path(SOURCE_DIRECTORY))?;
+    create_dir(&path(INCLUDE_PATH))?;
+
+    for (file, contents) in source_files(name, template) {
+        let file = path(&file);
+        if !file.exists() {
+            fs::write(&file, contents).map_err(|e| format!("failed to write '{}': {e}", file.display()))?;
+        }
+    }
+    if git {
+        init_git_repository(dir)?;
+        write_gitignore(dir, name, template)?;
+    }
+
+    // written last, so that a project that failed to be created can be created again
+    fs::write(&build_config_file, build_config(name, template)).map_err(|e| format!("failed to write '{}': {e}", build_config_file.display()))
+}
+
+/// Creates the project called `name` in a new directory of the same name.
+pub fn new_project(name : &str, template : Template, git : bool) -> Result<(), String>
+{
+    validate_package_name(name)?;
+    let dir = Path::new(name);
+    if dir.exists() {
+        return Err(format!("destination '{name}' already exists, use 'quikc init' to create a project in an existing directory"));
+    }
+    create_project(dir, name, template, git)
+}
+
+/// Creates a project in the current directory. Without a `name`, the project
+/// is named after the directory.
+pub fn init_project(name : Option<&str>, template : Template, git : bool) -> Result<String, String>
+{
+    let name = match name {
+        Some(name) => name.to_string(),
+        None => {
+            let dir = std::env::current_dir().map_err(|e| format!("failed to get the current directory: {e}"))?;
+            let name = dir.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+            validate_package_name(&name).map_err(|e| format!("{e}, pass the name with 'quikc init <NAME>'"))?;
+            name
+        }
+    };
+    create_project(Path::new("."), &name, template, git)?;
+    Ok(name)
+}
@@ -1,14 +1,15 @@
 use std::{process::Command, fs::{self}, env, path::Path, time::{SystemTime}, collections::HashMap, io::Write};
 #[allow(unused_imports)]
-use crate::{defaultbuild::{GCC_AND_CLANG_LINKER_OPTIONS, GCC_COMPILER_C_EXCLUSIVE_WARNINGS, GCC_COMPILER_NONEXCLUSIVE_WARNINGS, GCC_AND_CLANG_DIALECT_OPTIONS, CLANG_COMPILER_NONEXCLUSIVE_WARNINGS, GCC_COMPILER_CPP_EXCLUSIVE_WARNINGS, GCC_COMPILER_CPP_DIALECT_OPTIONS, CLANG_COMPILER_CPP_WARNINGS, GCC_AND_CLANG_CPP_DIALECT_OPTIONS, GCC_AND_CLANG_OPTIMIZATION_OPTIONS, GCC_STATIC_ANALYSIS_OPTIONS, GCC_AND_CLANG_ENHANCED_OPTIMIZATION_OPTIONS}, build::{DEFAULT_C_STANDARD, DEFAULT_CPP_STANDARD}, version::VERSIONS};
+use crate::{defaultbuild::{GCC_AND_CLANG_LINKER_OPTIONS, GCC_COMPILER_C_EXCLUSIVE_WARNINGS, GCC_COMPILER_NONEXCLUSIVE_WARNINGS, GCC_AND_CLANG_DIALECT_OPTIONS, CLANG_COMPILER_NONEXCLUSIVE_WARNINGS, GCC_COMPILER_CPP_EXCLUSIVE_WARNINGS, GCC_COMPILER_CPP_DIALECT_OPTIONS, CLANG_COMPILER_CPP_WARNINGS, GCC_AND_CLANG_CPP_DIALECT_OPTIONS, GCC_STATIC_ANALYSIS_OPTIONS}, build::{DEFAULT_C_STANDARD, DEFAULT_CPP_STANDARD}, version::VERSIONS};
 
 use color_print::cprintln;
 use const_format::concatcp;
 use filetime::{set_file_mtime};
+use walkdir::WalkDir;
 #[cfg(feature = "quikc-nightly")]
 use crate::version::NIGHTLY_VERSION;
 
-use crate::{build::{BUILD_CONFIG_FILE, Build}, SOURCE_DIRECTORY, compiler::{INCLUDE_PATH, compile_to_object_files, is_c_source_file, is_cpp_source_file, is_header_file}, buildtable::{BuildTable, BUILD_TABLE_OBJECT_FILE_DIRECTORY, get_duration_since_modified, BUILD_TABLE_ASM_DIRECTORY}, walker, linker::link_files, set_flags};
+use crate::{build::{BUILD_CONFIG_FILE, DEV_PROFILE, RELEASE_PROFILE, Build, Environment, Warnings, WarningLevel, Override, FileGlobs, Profile, TargetKind, Visibility, OptimizationLevel, LtoMode, resolve_profile}, SOURCE_DIRECTORY, compiler::{self, INCLUDE_PATH, compile_to_object_files, to_output_file, is_c_source_file, is_cpp_source_file, is_header_file}, buildtable::{BUILD_TABLE_DIRECTORY, BuildTable, BuildDirectories, FileRecord, get_duration_since_modified, read_link_table}, walker, linker::{link_files, link_command, target_object_files, target_up_to_date}, depfile, set_flags, QuikcFlags, cli::{self, Subcommand}, clean::{CleanScope, human_readable_size}, project::{self, Template}, compdb::{COMPILE_COMMANDS_FILE, json_string}, toolchain::{CompilerFamily, CompilerInfo, TOOLCHAIN_CACHE_FILE, FLAG_CACHE_FILE, parse_version_output, unsupported_flags}};
 
 const TOTAL_SOURCE_FILES : usize = 3;
 const TEST_FILES_DIR : &str = "../testfiles";
@@ -18,7 +19,7 @@ pub struct Tools
 {
     pub build_config : Build,
     pub source_files : Vec<String>,
-    pub old_table : HashMap<String, u64>,
+    pub old_table : HashMap<String, FileRecord>,
     pub build_table : BuildTable
 }
 
@@ -34,7 +35,7 @@ impl Tools
         let build_config = Build::new();
         let mut old_table = HashMap::new();
         let source_files = Vec::new();
-        let build_table = BuildTable::new(&mut old_table);
+        let build_table = BuildTable::new(&build_config, &mut old_table);
 
         Tools {
             build_config,
@@ -45,6 +46,25 @@ impl Tools
     }
 }
 
+// The options builds without debug information are optimized with by default
+const RELEASE_OPTIMIZATION_OPTIONS : [&str; 2] = ["-O2", "-flto"];
+const AGGRESSIVE_OPTIMIZATION_OPTIONS : [&str; 2] = ["-O3", "-flto"];
+
+/// Returns the build directories of the profile the test projects are built with.
+fn dirs() -> BuildDirectories
+{
+    BuildDirectories::new(DEV_PROFILE)
+}
+
+/// Returns the number of files in `dir`, including the ones in its subdirectories.
+fn count_files(dir : &str) -> usize
+{
+    WalkDir::new(dir).into_iter()
+                     .flatten()
+                     .filter(|entry| entry.file_type().is_file())
+                     .count()
+}
+
 #[inline]
 fn get_source_file(file_name : &str) -> String
 {
@@ -70,6 +90,21 @@ pub fn modify_file_time(file : &str) -> Result<(), Box<dyn std::error::Error>>
     Ok(())
 }
 
+/// Changes the contents of the file (by appending a newline) along with
+/// the time it was modified
+pub fn modify_file(file : &str) -> Result<(), Box<dyn std::error::Error>>
+{
+    let time = get_duration_since_modified(&fs::metadata(file)?);
+    // file system timestamps can be coarser than a millisecond, so make sure the new time is different
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    fs::OpenOptions::new().append(true).open(file)?.write_all(b"\n")?;
+    let new_time = get_duration_since_modified(&fs::metadata(file)?);
+
+    assert_ne!(time, new_time);
+
+    Ok(())
+}
+
 fn write_to_config(build_config : &Build) -> Result<(), Box<dyn std::error::Error>>
 {
     let mut file = fs::File::create(BUILD_CONFIG_FILE)?;
@@ -82,7 +117,7 @@ fn write_to_config(build_config : &Build) -> Result<(), Box<dyn std::error::Erro
 #[inline]
 fn get_src_files(tools : &mut Tools)
 {
-    tools.source_files = walker::retrieve_source_files(SOURCE_DIRECTORY, 
+    tools.source_files = walker::retrieve_source_files(&tools.build_config,
                                   &mut tools.build_table,
                                   &tools.old_table);
     
@@ -102,12 +137,7 @@ pub fn initialize_project(setup_additional_files : bool,
 
     to_test_directory()?;
 
-    let status = Command::new("python")
-                                     .arg("../quikc-init")
-                                     .arg(TEST_PACKAGE_NAME)
-                                     .spawn()?
-                                     .wait()?;
-    assert!(status.success());
+    project::init_project(Some(TEST_PACKAGE_NAME), Template::C, false)?;
 
     if settings.use_clang {
         let clang_build_toml = format!("{TEST_FILES_DIR}/config/{BUILD_CONFIG_FILE}");
@@ -172,6 +202,9 @@ pub fn test_all() -> Result<(), Box<dyn std::error::Error>>
         test_quikc_init(&settings)?;
         reset()?;
 
+        test_new_project(&settings)?;
+        reset()?;
+
         test_config(&settings)?;
         reset()?;
 
@@ -190,9 +223,51 @@ pub fn test_all() -> Result<(), Box<dyn std::error::Error>>
         test_recompile_after_config_change(&settings)?;
         reset()?;
 
+        test_relink_when_out_of_date(&settings)?;
+        reset()?;
+
         test_recompile_after_deletion(&settings)?;
         reset()?;
 
+        test_nested_source_directories(&settings)?;
+        reset()?;
+
+        test_mixed_languages(&settings)?;
+        reset()?;
+
+        test_compiler_detection(&settings)?;
+        reset()?;
+
+        test_flag_probing(&settings)?;
+        reset()?;
+
+        test_environment(&settings)?;
+        reset()?;
+
+        test_warning_policy(&settings)?;
+        reset()?;
+
+        test_file_overrides(&settings)?;
+        reset()?;
+
+        test_third_party_sources(&settings)?;
+        reset()?;
+
+        test_defines_and_include_dirs(&settings)?;
+        reset()?;
+
+        test_compile_commands(&settings)?;
+        reset()?;
+
+        test_clean(&settings)?;
+        reset()?;
+
+        test_source_configuration(&settings)?;
+        reset()?;
+
+        test_profiles(&settings)?;
+        reset()?;
+
         test_recompilation_after_deleting_binary(&settings)?;
         reset()?;
 
@@ -205,6 +280,18 @@ pub fn test_all() -> Result<(), Box<dyn std::error::Error>>
         test_execute_linker_with_build_info(&settings)?;
         reset()?;
 
+        test_multiple_targets(&settings)?;
+        reset()?;
+
+        test_run(&settings)?;
+        reset()?;
+
+        test_static_library(&settings)?;
+        reset()?;
+
+        test_shared_library(&settings)?;
+        reset()?;
+
         settings.use_clang = true;
     }
 
@@ -217,7 +304,7 @@ fn test_quikc_init(settings : &Settings) ->  Result<(), Box<dyn std::error::Erro
 
 
     // 'initialize_project' will create many source files, however the file generated
-    // by the 'quikc-init' command is the only one we need to check for. The other ones
+    // by 'quikc init' is the only one we need to check for. The other ones
     // are for testing purposes only, which is why only 'source_file' is checked
     initialize_project(false, false, settings)?;
 
@@ -227,10 +314,70 @@ fn test_quikc_init(settings : &Settings) ->  Result<(), Box<dyn std::error::Erro
     assert!(Path::new(SOURCE_DIRECTORY).is_dir());
     assert!(Path::new(INCLUDE_PATH).is_dir());
     assert!(Path::new(&source_file).is_file());
+
+    // an existing project is never overwritten
+    fs::write(&source_file, "int main(void) { return 1; }\n")?;
+    let build_config = fs::read_to_string(BUILD_CONFIG_FILE)?;
+    assert!(project::init_project(Some("other"), Template::Cpp, false).is_err());
+    assert_eq!(fs::read_to_string(BUILD_CONFIG_FILE)?, build_config);
+    assert_eq!(fs::read_to_string(&source_file)?, "int main(void) { return 1; }\n");
     
     Ok(())
 }
 
+fn test_new_project(_settings : &Settings) -> Result<(), Box<dyn std::error::Error>>
+{
+    to_test_directory()?;
+
+    // every template but the embedded one (which has no entry point) builds as it is created
+    let templates = [("c", "c-project"), ("cpp", "cpp-project"), ("static", "libstatic-project.a"), ("shared", "libshared-project.so")];
+    for (template, output_file) in templates {
+        let name = format!("{template}-project");
+        run(&["new", &name, "--template", template, "--git", "-qq"].to_vec());
+        env::set_current_dir(&name)?;
+        run(&["-qq"].to_vec());
+        assert!(Path::new(output_file).exists(), "the {template} template did not build");
+
+        let gitignore = fs::read_to_string(".gitignore")?;
+        assert!(Path::new(".git").is_dir());
+        assert!(gitignore.lines().any(|line| line == "/buildinfo/"));
+        env::set_current_dir("..")?;
+    }
+
+    run(&["new", "embedded-project", "-t", "embedded", "-qq"].to_vec());
+    assert!(Path::new("./embedded-project/Build.toml").is_file());
+    assert!(!Path::new("./embedded-project/.gitignore").exists());
+
+    // the C++ variant is compiled with g++, without the C++ standard library's headers
+    run(&["new", "embedded-cpp-project", "-t", "embedded-cpp", "-qq"].to_vec());
+    env::set_current_dir("embedded-cpp-project")?;
+    let build = Build::new();
+    assert_eq!(build.cpp_compiler(), "g++");
+    assert!(build.compiler.cpp_args.as_ref().unwrap().contains(&"-nostdinc++".to_string()));
+    assert!(build.execute_compiler_with_build_info("main.cc").get_args().any(|arg| arg == "-nostdinc++"));
+    assert!(!build.execute_compiler_with_build_info("main.c").get_args().any(|arg| arg == "-nostdinc++"));
+    env::set_current_dir("..")?;
+
+    // 'new' refuses existing directories, and 'init' existing projects
+    let build_config = fs::read_to_string("./c-project/Build.toml")?;
+    run(&["new", "c-project", "-t", "cpp", "-qq"].to_vec());
+    env::set_current_dir("./c-project")?;
+    run(&["init", "-t", "cpp", "-qq"].to_vec());
+    assert_eq!(fs::read_to_string(BUILD_CONFIG_FILE)?, build_config);
+    assert!(!Path::new("./src/c-project.cc").exists());
+    env::set_current_dir("..")?;
+
+    // without a name, 'init' names the project after its directory
+    fs::create_dir("./named-project")?;
+    env::set_current_dir("./named-project")?;
+    run(&["init", "-qq"].to_vec());
+    assert!(Path::new("./src/named-project.c").is_file());
+    assert!(fs::read_to_string(BUILD_CONFIG_FILE)?.contains("name = \"named-project\""));
+    env::set_current_dir("..")?;
+
+    Ok(())
+}
+
 /// This will treat the project as if it needs to be rebuilt entirely.
 fn test_first_time_compilation(settings : &Settings) -> Result<(), Box<dyn std::error::Error>>
 {
@@ -242,7 +389,10 @@ fn test_first_time_compilation(settings : &Settings) -> Result<(), Box<dyn std::
 
     let compilation_success = compile_to_object_files(&tools.source_files, &tools.build_config);
     assert!(compilation_success);
-    assert_eq!(fs::read_dir(BUILD_TABLE_OBJECT_FILE_DIRECTORY)?.count(), TOTAL_SOURCE_FILES);
+    assert_eq!(count_files(&dirs().obj), TOTAL_SOURCE_FILES);
+    // every object file should have a dependency file written along with it
+    assert_eq!(count_files(&dirs().deps), TOTAL_SOURCE_FILES);
+    assert!(tools.build_table.get_file_dependencies(get_source_file("main.c").as_str()).contains("include/hi.h"));
 
     let link_success = link_files(&tools.build_config);
     assert!(link_success);
@@ -259,10 +409,20 @@ fn test_recompilation(settings : &Settings) -> Result<(), Box<dyn std::error::Er
     // note we introduce different scopes so the build table file is written to
     // once the build table has been dropped
 
+    // Touching the files without changing their contents should not cause recompilation
+    {
+        modify_file_time(get_source_file("main.c").as_str())?;
+        modify_file_time(format!("{}/{}", INCLUDE_PATH, "hi.h").as_str())?;
+        let mut tools = Tools::new();
+        get_src_files(&mut tools);
+
+        assert_eq!(tools.source_files.len(), 0);
+    }
+
     // Compiled it once, now we modify a specific source file and recompile
     {
         let source_file_to_modify = get_source_file("main.c");
-        modify_file_time(source_file_to_modify.as_str())?;
+        modify_file(source_file_to_modify.as_str())?;
         let mut tools = Tools::new();
         get_src_files(&mut tools);
 
@@ -271,7 +431,7 @@ fn test_recompilation(settings : &Settings) -> Result<(), Box<dyn std::error::Er
         let compilation_success = compile_to_object_files(&tools.source_files, &tools.build_config);
 
         assert!(compilation_success);
-        assert_eq!(fs::read_dir(BUILD_TABLE_OBJECT_FILE_DIRECTORY)?.count(), TOTAL_SOURCE_FILES);
+        assert_eq!(count_files(&dirs().obj), TOTAL_SOURCE_FILES);
 
         let link_success = link_files(&tools.build_config);
         assert!(link_success);
@@ -281,7 +441,7 @@ fn test_recompilation(settings : &Settings) -> Result<(), Box<dyn std::error::Er
     // depended on it need to be recompiled
     {
         let header_file_to_modify = format!("{}/{}", INCLUDE_PATH, "hi.h");
-        modify_file_time(header_file_to_modify.as_str())?;
+        modify_file(header_file_to_modify.as_str())?;
         let mut tools = Tools::new();
         get_src_files(&mut tools);
 
@@ -290,7 +450,7 @@ fn test_recompilation(settings : &Settings) -> Result<(), Box<dyn std::error::Er
 
         let compilation_success = compile_to_object_files(&tools.source_files, &tools.build_config);
         assert!(compilation_success);
-        assert_eq!(fs::read_dir(BUILD_TABLE_OBJECT_FILE_DIRECTORY)?.count(), TOTAL_SOURCE_FILES);
+        assert_eq!(count_files(&dirs().obj), TOTAL_SOURCE_FILES);
 
         let link_success = link_files(&tools.build_config);
         assert!(link_success);
@@ -319,8 +479,9 @@ fn test_invalid_file_recompiles(settings : &Settings) -> Result<(), Box<dyn std:
         assert!(!compilation_success); 
 
         // There should only be 'TOTAL_FILES - 1' object files since the invalid file
-        // did not compile successfully
-        assert_eq!(fs::read_dir(BUILD_TABLE_OBJECT_FILE_DIRECTORY)?.count(), TOTAL_FILES - 1);
+        // did not compile successfully, and the same goes for the dependency files
+        assert_eq!(count_files(&dirs().obj), TOTAL_FILES - 1);
+        assert_eq!(count_files(&dirs().deps), TOTAL_FILES - 1);
     }
 
     // Now we compile again
@@ -349,7 +510,7 @@ fn test_recompile_after_config_change(settings : &Settings) -> Result<(), Box<dy
     let compilation_success = compile_to_object_files(&tools.source_files, &tools.build_config);
 
     assert!(compilation_success);
-    assert_eq!(fs::read_dir(BUILD_TABLE_OBJECT_FILE_DIRECTORY)?.count(), TOTAL_SOURCE_FILES);
+    assert_eq!(count_files(&dirs().obj), TOTAL_SOURCE_FILES);
 
     let link_success = link_files(&tools.build_config);
     assert!(link_success);
@@ -357,13 +518,69 @@ fn test_recompile_after_config_change(settings : &Settings) -> Result<(), Box<dy
     Ok(())
 }
 
+/// Returns true if every target in the build configuration is up to date.
+fn targets_up_to_date(build_config : &Build) -> bool
+{
+    let link_table = read_link_table(&build_config.dirs);
+    build_config.targets.iter().all(|target| {
+        let object_files = target_object_files(build_config, target);
+        let cmd = link_command(build_config, target, &object_files);
+        target_up_to_date(target, &object_files, &cmd, &link_table)
+    })
+}
+
+/// Tests if the targets are relinked only when the linker configuration or
+/// one of the object files changed.
+fn test_relink_when_out_of_date(settings : &Settings) -> Result<(), Box<dyn std::error::Error>>
+{
+    test_first_time_compilation(settings)?;
+    assert!(targets_up_to_date(&Build::new()));
+
+    // Changing the linker arguments should not recompile anything, but the
+    // targets have to be relinked
+    {
+        let mut build = Build::new();
+        build.linker.args = Some(vec!["-s".to_string()]);
+        build.compiler.cstd = None;
+        build.compiler.cppstd = None;
+        write_to_config(&build)?;
+
+        let mut tools = Tools::new();
+        get_src_files(&mut tools);
+
+        assert_eq!(tools.source_files.len(), 0);
+        assert!(!targets_up_to_date(&tools.build_config));
+        assert!(link_files(&tools.build_config));
+        assert!(targets_up_to_date(&tools.build_config));
+    }
+
+    // An object file that is newer than the executable should cause a relink
+    {
+        let object_file = to_output_file(Path::new(&get_source_file("main.c")), &dirs().obj, "o");
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        set_file_mtime(&object_file, SystemTime::now().into())?;
+
+        let build = Build::new();
+        assert!(!targets_up_to_date(&build));
+        assert!(link_files(&build));
+        assert!(targets_up_to_date(&build));
+    }
+
+    Ok(())
+}
+
 /// Tests if the entire project will recompile if a source file has been deleted.
 fn test_recompile_after_deletion(settings : &Settings) -> Result<(), Box<dyn std::error::Error>>
 {
     const FILE_TO_BE_DELETED : &str = "dep.c";
     test_first_time_compilation(settings)?;
 
+    // the compile command changes along with the deletion, so the records of the
+    // recompiled files have to be updated to their new commands
     fs::remove_file(get_source_file(FILE_TO_BE_DELETED))?;
+    let mut build = Build::new();
+    build.compiler.defines = Some(vec!["AFTER_DELETION".to_string()]);
+    write_build(build)?;
     
     const NUM_FILES_AFTER_DELETION : usize = TOTAL_SOURCE_FILES - 1;
     // Once the file is removed, recompilation should begin
@@ -376,7 +593,7 @@ fn test_recompile_after_deletion(settings : &Settings) -> Result<(), Box<dyn std
         let compilation_success = compile_to_object_files(&tools.source_files, &tools.build_config);
 
         assert!(compilation_success);
-        assert_eq!(fs::read_dir(BUILD_TABLE_OBJECT_FILE_DIRECTORY)?.count(), NUM_FILES_AFTER_DELETION);
+        assert_eq!(count_files(&dirs().obj), NUM_FILES_AFTER_DELETION);
         assert!(!tools.build_table.contains(format!("{SOURCE_DIRECTORY}/{FILE_TO_BE_DELETED}").as_str()));
 
         let link_success = link_files(&tools.build_config);
@@ -384,6 +601,11 @@ fn test_recompile_after_deletion(settings : &Settings) -> Result<(), Box<dyn std
 
     }
 
+    // nothing is recompiled on the next build
+    let mut tools = Tools::new();
+    get_src_files(&mut tools);
+    assert!(tools.source_files.is_empty());
+
     Ok(())
 }
 
@@ -397,7 +619,7 @@ fn test_recompilation_after_deleting_binary(settings : &Settings) -> Result<(),
     get_src_files(&mut tools);
 
     assert_eq!(tools.source_files.len(), 0);
-    assert_eq!(fs::read_dir(BUILD_TABLE_OBJECT_FILE_DIRECTORY)?.count(), TOTAL_SOURCE_FILES);
+    assert_eq!(count_files(&dirs().obj), TOTAL_SOURCE_FILES);
 
     let link_success = link_files(&tools.build_config);
     assert!(link_success);
@@ -405,6 +627,654 @@ fn test_recompilation_after_deleting_binary(settings : &Settings) -> Result<(),
     Ok(())
 }
 
+/// Tests if source files in subdirectories of 'src' are compiled, even if their
+/// names collide with other source files, and if their object files are removed
+/// once they are deleted.
+fn test_nested_source_directories(settings : &Settings) -> Result<(), Box<dyn std::error::Error>>
+{
+    initialize_project(true, false, settings)?;
+    for (dir, function) in [("net", "net_util"), ("fs", "fs_util")] {
+        fs::create_dir(get_source_file(dir))?;
+        fs::write(get_source_file(&format!("{dir}/util.c")), format!("int {function}(void) {{ return 0; }}\n"))?;
+    }
+    // same name as 'main.c', but a different extension
+    fs::write(get_source_file("main.cpp"), "int main_cpp() { return 0; }\n")?;
+
+    const TOTAL_FILES : usize = TOTAL_SOURCE_FILES + 3;
+    {
+        let mut tools = Tools::new();
+        get_src_files(&mut tools);
+
+        assert_eq!(tools.source_files.len(), TOTAL_FILES);
+        assert!(compile_to_object_files(&tools.source_files, &tools.build_config));
+        assert_eq!(count_files(&dirs().obj), TOTAL_FILES);
+        assert_eq!(count_files(&dirs().deps), TOTAL_FILES);
+        assert!(Path::new(&format!("{}/src/net/util.c.o", dirs().obj)).is_file());
+        assert!(Path::new(&format!("{}/src/main.cpp.o", dirs().obj)).is_file());
+        assert!(link_files(&tools.build_config));
+    }
+
+    // Deleting a nested source file removes its object file as well
+    fs::remove_file(get_source_file("fs/util.c"))?;
+    let mut tools = Tools::new();
+    get_src_files(&mut tools);
+
+    assert_eq!(tools.source_files.len(), TOTAL_FILES - 1);
+    assert_eq!(count_files(&dirs().obj), TOTAL_FILES - 1);
+    assert!(!Path::new(&format!("{}/src/fs/util.c.d", dirs().deps)).exists());
+
+    Ok(())
+}
+
+/// Tests if C and C++ source files are compiled with their own compilers and
+/// arguments, and if targets with C++ objects are linked with the C++ compiler.
+fn test_mixed_languages(settings : &Settings) -> Result<(), Box<dyn std::error::Error>>
+{
+    initialize_project(true, false, settings)?;
+    // operator new and delete are only found in the C++ standard library
+    fs::write(get_source_file("value.cpp"), "int cpp_value();\nint cpp_value() { int* value = new int(1); int v = *value; delete value; return v; }\n")?;
+
+    let (c_compiler, cpp_compiler) = match settings.use_clang {
+        true => ("clang", "clang++"),
+        false => ("gcc", "g++")
+    };
+    {
+        let mut tools = Tools::new();
+        assert_eq!(tools.build_config.c_compiler(), c_compiler);
+        assert_eq!(tools.build_config.cpp_compiler(), cpp_compiler);
+        assert_eq!(tools.build_config.execute_compiler_with_build_info("./src/main.c").get_program(), c_compiler);
+        assert_eq!(tools.build_config.execute_compiler_with_build_info("./src/value.cpp").get_program(), cpp_compiler);
+        get_src_files(&mut tools);
+
+        assert!(compile_to_object_files(&tools.source_files, &tools.build_config));
+        let target = &tools.build_config.targets[0];
+        let object_files = target_object_files(&tools.build_config, target);
+        assert_eq!(link_command(&tools.build_config, target, &object_files).get_program(), cpp_compiler);
+        // the objects are linked in a fixed order, whatever order the file system lists them in
+        let mut sorted_object_files = object_files.clone();
+        sorted_object_files.sort();
+        assert_eq!(object_files, sorted_object_files);
+        assert!(link_files(&tools.build_config));
+    }
+
+    // The C++ compiler is derived from a compiler given by its path or behind a launcher
+    let mut build = Build::new();
+    build.compiler.compiler = format!("env {c_compiler}");
+    build.compiler.c_compiler = None;
+    build.compiler.cpp_compiler = None;
+    write_build(build)?;
+    {
+        let mut tools = Tools::new();
+        assert_eq!(tools.build_config.cpp_compiler(), format!("env {cpp_compiler}"));
+        get_src_files(&mut tools);
+        assert!(compile_to_object_files(&tools.source_files, &tools.build_config));
+        assert!(link_files(&tools.build_config));
+    }
+    let mut build = Build::new();
+    build.compiler.compiler = c_compiler.to_string();
+    build.compiler.c_compiler = None;
+    build.compiler.cpp_compiler = None;
+    write_build(build)?;
+
+    // Without C++ objects, the C compiler links the target
+    fs::remove_file(get_source_file("value.cpp"))?;
+    {
+        let mut tools = Tools::new();
+        get_src_files(&mut tools);
+        let target = &tools.build_config.targets[0];
+        let object_files = target_object_files(&tools.build_config, target);
+        assert_eq!(link_command(&tools.build_config, target, &object_files).get_program(), c_compiler);
+    }
+
+    // Arguments of one language do not replace the default configuration of the other
+    let mut build = Build::new();
+    build.compiler.cpp_args = Some(vec!["-DCPP_ONLY".to_string()]);
+    write_build(build)?;
+
+    let build = Build::new();
+    let c_args = build.execute_compiler_with_build_info("test.c").get_args().map(|arg| arg.to_str().unwrap().to_string()).collect::<Vec<String>>();
+    let cpp_args = build.execute_compiler_with_build_info("test.cpp").get_args().map(|arg| arg.to_str().unwrap().to_string()).collect::<Vec<String>>();
+    assert!(c_args.contains(&"-Wall".to_string()) && !c_args.contains(&"-DCPP_ONLY".to_string()));
+    assert!(cpp_args.contains(&"-DCPP_ONLY".to_string()) && !cpp_args.contains(&"-Wall".to_string()));
+
+    Ok(())
+}
+
+/// Tests if the compiler family is detected from the compiler's version output
+/// (no matter how the compiler is invoked) and if the result is cached.
+fn test_compiler_detection(settings : &Settings) -> Result<(), Box<dyn std::error::Error>>
+{
+    initialize_project(true, false, settings)?;
+    let (compiler, family) = match settings.use_clang {
+        true => ("clang", CompilerFamily::Clang),
+        false => ("gcc", CompilerFamily::Gcc)
+    };
+
+    let build = Build::new();
+    assert_eq!(build.compiler_family(build.c_compiler()), family);
+    assert!(fs::read_to_string(TOOLCHAIN_CACHE_FILE)?.lines().any(|line| line.starts_with(&format!("{compiler}="))));
+
+    // A compiler behind a launcher still gets the default configuration of its family
+    let launched_compiler = format!("env {compiler}");
+    let mut build = Build::new();
+    build.compiler.c_compiler = Some(launched_compiler.clone());
+    write_build(build)?;
+
+    let build = Build::new();
+    assert_eq!(build.compiler_family(&launched_compiler), family);
+    let cmd = build.execute_compiler_with_build_info("test.c");
+    assert_eq!(cmd.get_program(), "env");
+    assert!(cmd.get_args().any(|arg| arg == compiler) && cmd.get_args().any(|arg| arg == "-Wall"));
+
+    // The compilers are not run again while they are unchanged, so the cached family is used
+    let cache = fs::read_to_string(TOOLCHAIN_CACHE_FILE)?;
+    fs::write(TOOLCHAIN_CACHE_FILE, cache.replace(&format!(":{}:", compiler), ":unknown:"))?;
+    let build = Build::new();
+    assert_eq!(build.compiler_family(&launched_compiler), CompilerFamily::Unknown);
+
+    Ok(())
+}
+
+/// Tests if the default flags a compiler does not support are found, cached and left out.
+fn test_flag_probing(settings : &Settings) -> Result<(), Box<dyn std::error::Error>>
+{
+    initialize_project(true, false, settings)?;
+
+    let build = Build::new();
+    let c_compiler = build.c_compiler().to_string();
+    assert!(build.unsupported_flags(true).is_empty());
+    assert!(fs::read_to_string(FLAG_CACHE_FILE)?.lines().any(|line| line.ends_with("\t-Wall\t1")));
+
+    let unsupported = unsupported_flags(&c_compiler, &build.compilers[&c_compiler], true, &["-Wall", "-fquikc-unknown-flag"]);
+    assert_eq!(unsupported, vec!["-fquikc-unknown-flag"]);
+    assert!(fs::read_to_string(FLAG_CACHE_FILE)?.lines().any(|line| line.ends_with("\t-fquikc-unknown-flag\t0")));
+
+    // Pretend the C compiler does not support one of the default warnings
+    let cache = fs::read_to_string(FLAG_CACHE_FILE)?;
+    let cache = cache.lines()
+                     .map(|line| match line.strip_suffix("\tc\t-Wconversion\t1") {
+                         Some(compiler) => format!("{compiler}\tc\t-Wconversion\t0\n"),
+                         None => format!("{line}\n")
+                     })
+                     .collect::<String>();
+    fs::write(FLAG_CACHE_FILE, cache)?;
+
+    let build = Build::new();
+    assert_eq!(build.unsupported_flags(true), ["-Wconversion"]);
+    let c_args = build.execute_compiler_with_build_info("test.c").get_args().map(|arg| arg.to_str().unwrap().to_string()).collect::<Vec<String>>();
+    let cpp_args = build.execute_compiler_with_build_info("test.cpp").get_args().map(|arg| arg.to_str().unwrap().to_string()).collect::<Vec<String>>();
+    assert!(!c_args.contains(&"-Wconversion".to_string()) && c_args.contains(&"-Wall".to_string()));
+    assert!(cpp_args.contains(&"-Wconversion".to_string()));
+    assert!(run(&[].to_vec()).contains("does not support the default flags -Wconversion"));
+
+    Ok(())
+}
+
+/// Tests if the flags from the environment are added after the configured
+/// arguments, and only for the language (or the linker) they are meant for.
+fn test_environment(settings : &Settings) -> Result<(), Box<dyn std::error::Error>>
+{
+    initialize_project(true, false, settings)?;
+
+    let mut build = Build::new();
+    build.environment = Environment {
+        cppflags : vec!["-DQUIKC_CPPFLAGS".to_string()],
+        cflags : vec!["-O1".to_string(), "-fno-common".to_string()],
+        cxxflags : vec!["-DQUIKC_CXXFLAGS".to_string()],
+        ldflags : vec!["-Wl,-z,relro".to_string()],
+        ..Environment::default()
+    };
+    let args = |cmd : Command| cmd.get_args().map(|arg| arg.to_str().unwrap().to_string()).collect::<Vec<String>>();
+
+    let c_args = args(build.execute_compiler_with_build_info("test.c"));
+    assert!(c_args.ends_with(&["-DQUIKC_CPPFLAGS".to_string(), "-O1".to_string(), "-fno-common".to_string()]));
+    assert!(c_args.contains(&"-Wall".to_string()));
+    let cpp_args = args(build.execute_compiler_with_build_info("test.cpp"));
+    assert!(cpp_args.ends_with(&["-DQUIKC_CPPFLAGS".to_string(), "-DQUIKC_CXXFLAGS".to_string()]));
+    assert!(!cpp_args.contains(&"-fno-common".to_string()));
+    let link_args = args(build.execute_linker_with_build_info(&build.targets[0], &[]));
+    assert!(link_args.contains(&"-Wl,-z,relro".to_string()));
+
+    let output = run(&["config"].to_vec());
+    assert!(output.contains("C compiler:") && output.contains(build.c_compiler()));
+    assert!(output.contains("-std=c17"));
+    // the project is only configured, not built
+    assert!(!Path::new(TEST_PACKAGE_NAME).exists());
+
+    // a target with C++ sources is linked with the C++ compiler, even before it was built
+    fs::write(get_source_file("value.cpp"), "int cpp_value();\nint cpp_value() { return 1; }\n")?;
+    assert!(run(&["config"].to_vec()).contains(&format!("with {}):", build.cpp_compiler())));
+
+    Ok(())
+}
+
+/// Tests if the warning level, warnings as errors and the allowed and denied
+/// warnings from the '[warnings]' section are honored.
+fn test_warning_policy(settings : &Settings) -> Result<(), Box<dyn std::error::Error>>
+{
+    initialize_project(true, false, settings)?;
+    let args = |build : &Build| build.execute_compiler_with_build_info("test.c")
+                                     .get_args()
+                                     .map(|arg| arg.to_str().unwrap().to_string())
+                                     .collect::<Vec<String>>();
+    let contains = |args : &Vec<String>, flag : &str| args.iter().any(|arg| arg == flag);
+
+    let mut build = Build::new();
+    let default_args = args(&build);
+    assert!(contains(&default_args, "-Werror") && contains(&default_args, "-Wconversion"));
+    assert!(!contains(&default_args, "-Wundef"));
+
+    build.warnings.level = Some(WarningLevel::Strict);
+    assert!(contains(&args(&build), "-Wundef") && contains(&args(&build), "-Werror"));
+
+    build.warnings.level = Some(WarningLevel::Relaxed);
+    let relaxed_args = args(&build);
+    assert!(contains(&relaxed_args, "-Wall") && contains(&relaxed_args, "-Wextra"));
+    assert!(!contains(&relaxed_args, "-Wconversion") && !contains(&relaxed_args, "-Werror"));
+
+    build.warnings.level = Some(WarningLevel::None);
+    build.warnings.as_errors = Some(true);
+    let none_args = args(&build);
+    assert!(!contains(&none_args, "-Wall") && contains(&none_args, "-Werror"));
+
+    build.warnings = Warnings {
+        allow : Some(vec!["unused-variable".to_string()]),
+        deny : Some(vec!["-Wshadow".to_string()]),
+        ..Warnings::default()
+    };
+    let policy_args = args(&build);
+    assert!(policy_args.ends_with(&["-Wno-unused-variable".to_string(), "-Werror=shadow".to_string()]));
+    assert!(contains(&policy_args, "-Werror"));
+
+    // the allowed warnings are also passed when the default configuration is not used
+    build.compiler.args = Some(vec!["-Wall".to_string()]);
+    build.compiler.append_args = Some(false);
+    assert_eq!(args(&build)[args(&build).len() - 3..], ["-Wall", "-Wno-unused-variable", "-Werror=shadow"]);
+
+    // an unused variable is only allowed once the warning is
+    fs::write(get_source_file("unused.c"), "int unused(void) { int x = 0; return 1; }\n")?;
+    let mut build = Build::new();
+    build.warnings.allow = Some(vec!["unused-variable".to_string()]);
+    write_build(build)?;
+    assert!(run(&[].to_vec()).contains("Successfully"));
+
+    // warnings have to be given by name
+    let mut build = Build::new();
+    build.warnings.deny = Some(vec!["no-shadow".to_string()]);
+    write_build(build)?;
+    assert!(!run(&[].to_vec()).contains("Successfully"));
+
+    Ok(())
+}
+
+/// Tests if the '[[override]]' sections change the flags, defines and standards
+/// of the files that match them, and only of those files.
+fn test_file_overrides(settings : &Settings) -> Result<(), Box<dyn std::error::Error>>
+{
+    initialize_project(true, false, settings)?;
+    let args = |build : &Build, file : &str| build.execute_compiler_with_build_info(file)
+                                                  .get_args()
+                                                  .map(|arg| arg.to_str().unwrap().to_string())
+                                                  .collect::<Vec<String>>();
+    let contains = |args : &Vec<String>, flag : &str| args.iter().any(|arg| arg == flag);
+
+    let mut build = Build::new();
+    build.overrides = Some(vec![
+        Override {
+            files : FileGlobs::try_from(vec!["src/generated/**".to_string()])?,
+            remove_args : Some(vec!["-Wconversion".to_string()]),
+            defines : Some(vec!["GENERATED".to_string()]),
+            cstd : Some("c99".to_string()),
+            ..Override::default()
+        },
+        Override {
+            files : FileGlobs::try_from(vec!["**/simd.c".to_string()])?,
+            args : Some(vec!["-DSIMD".to_string()]),
+            cstd : Some("c11".to_string()),
+            ..Override::default()
+        }
+    ]);
+
+    let generated_args = args(&build, "./src/generated/parser.c");
+    assert!(contains(&generated_args, "-std=c99") && !contains(&generated_args, DEFAULT_C_STANDARD));
+    assert!(contains(&generated_args, "-DGENERATED") && !contains(&generated_args, "-Wconversion"));
+    assert!(!contains(&generated_args, "-DSIMD"));
+
+    // later overrides win, and the C standard is not used for C++ files
+    let simd_args = args(&build, "./src/generated/simd.c");
+    assert!(contains(&simd_args, "-std=c11") && contains(&simd_args, "-DGENERATED") && contains(&simd_args, "-DSIMD"));
+    assert!(contains(&args(&build, "./src/generated/parser.cpp"), DEFAULT_CPP_STANDARD));
+
+    let main_args = args(&build, "./src/main.c");
+    assert!(contains(&main_args, DEFAULT_C_STANDARD) && contains(&main_args, "-Wconversion"));
+    assert!(!contains(&main_args, "-DGENERATED"));
+
+    // the generated file only compiles with the override
+    fs::create_dir_all("src/generated")?;
+    fs::write("src/generated/parser.c", "#ifndef GENERATED\n#error not generated\n#endif\nint parse(long x);\nint parse(long x) { return x; }\n")?;
+    write_build(build)?;
+    assert!(run(&[].to_vec()).contains("Successfully"));
+
+    fs::write(BUILD_CONFIG_FILE, fs::read_to_string(BUILD_CONFIG_FILE)?.replace("src/generated/**", "src/[generated/**"))?;
+    assert!(!run(&[].to_vec()).contains("Successfully"));
+
+    Ok(())
+}
+
+/// Tests if the system include directories are passed with '-isystem' while their
+/// headers are still tracked, and if third-party sources get relaxed warnings.
+fn test_third_party_sources(settings : &Settings) -> Result<(), Box<dyn std::error::Error>>
+{
+    initialize_project(true, false, settings)?;
+    fs::create_dir_all("vendor/include")?;
+    fs::create_dir_all("src/vendor")?;
+    // both would fail to compile with the default warnings, since they are errors
+    fs::write("vendor/include/vendored.h", "static inline int narrow(long x) { return x; }\n")?;
+    fs::write(get_source_file("uses_vendor.c"), "#include <vendored.h>\nint use_vendor(void);\nint use_vendor(void) { return narrow(1L); }\n")?;
+    fs::write("src/vendor/legacy.c", "int legacy(void);\nint legacy(void) { int unused = 0; return 1; }\n")?;
+
+    let mut build = Build::new();
+    build.sources.system_include_dirs = Some(vec!["vendor/include".to_string()]);
+    build.sources.third_party = Some(FileGlobs::try_from(vec!["src/vendor/**".to_string()])?);
+    // a denied warning does not apply to third party files either
+    build.warnings.deny = Some(vec!["unused-variable".to_string()]);
+    write_build(build)?;
+
+    let build = Build::new();
+    assert!(build.include_flags().ends_with(&["-isystem".to_string(), "./vendor/include".to_string()]));
+    let args = |file : &str| build.execute_compiler_with_build_info(file)
+                                  .get_args()
+                                  .map(|arg| arg.to_str().unwrap().to_string())
+                                  .collect::<Vec<String>>();
+    let legacy_args = args("./src/vendor/legacy.c");
+    assert!(legacy_args.contains(&"-Wall".to_string()) && !legacy_args.contains(&"-Werror".to_string()));
+    assert!(!legacy_args.contains(&"-Wconversion".to_string()));
+    assert!(!legacy_args.contains(&"-Werror=unused-variable".to_string()));
+    assert!(args("./src/main.c").contains(&"-Werror".to_string()));
+    assert!(args("./src/main.c").contains(&"-Werror=unused-variable".to_string()));
+
+    assert!(run(&[].to_vec()).contains("Successfully"));
+
+    // only the source file that includes the vendored header is recompiled
+    modify_file("vendor/include/vendored.h")?;
+    let mut tools = Tools::new();
+    get_src_files(&mut tools);
+    assert_eq!(tools.source_files, vec![get_source_file("uses_vendor.c")]);
+
+    Ok(())
+}
+
+/// Tests if the global and per profile defines and include directories are passed
+/// even without the default configuration, and if changing them rebuilds the objects.
+fn test_defines_and_include_dirs(settings : &Settings) -> Result<(), Box<dyn std::error::Error>>
+{
+    initialize_project(true, false, settings)?;
+    fs::create_dir_all("dev_include")?;
+    fs::write("dev_include/dev.h", "#define DEV_HEADER 1\n")?;
+    fs::write(get_source_file("configured.c"), "#include <dev.h>\n#if !DEV_HEADER || VERSION != 1\n#error not configured\n#endif\n")?;
+
+    let mut build = Build::new();
+    build.compiler.defines = Some(vec!["VERSION=1".to_string()]);
+    build.profile = Some(HashMap::from([(DEV_PROFILE.to_string(), Profile {
+        include_dirs : Some(vec!["dev_include".to_string()]),
+        defines : Some(vec!["DEV".to_string()]),
+        ..Default::default()
+    })]));
+    // the defines and include directories do not depend on the default configuration
+    build.compiler.args = Some(vec!["-O1".to_string()]);
+    build.compiler.append_args = Some(false);
+    write_build(build)?;
+
+    {
+        let mut tools = Tools::new();
+        let args = tools.build_config.execute_compiler_with_build_info("test.c").get_args()
+                                     .map(|arg| arg.to_str().unwrap().to_string())
+                                     .collect::<Vec<String>>();
+        assert!(args.contains(&"-DVERSION=1".to_string()) && args.contains(&"-DDEV".to_string()));
+        assert_eq!(tools.build_config.include_flags().last(), Some(&"-I./dev_include".to_string()));
+
+        get_src_files(&mut tools);
+        assert_eq!(tools.source_files.len(), TOTAL_SOURCE_FILES + 1);
+        assert!(compile_to_object_files(&tools.source_files, &tools.build_config));
+    }
+
+    // changing a define changes the command of every object, so they are all rebuilt
+    let mut build = Build::new();
+    build.compiler.defines = Some(vec!["VERSION=2".to_string()]);
+    write_build(build)?;
+    let mut tools = Tools::new();
+    get_src_files(&mut tools);
+    assert_eq!(tools.source_files.len(), TOTAL_SOURCE_FILES + 1);
+    assert!(!compile_to_object_files(&tools.source_files, &tools.build_config));
+
+    Ok(())
+}
+
+/// Tests if the compilation database lists the complete command of every source
+/// file, on demand and on every build if it is enabled.
+fn test_compile_commands(settings : &Settings) -> Result<(), Box<dyn std::error::Error>>
+{
+    initialize_project(true, false, settings)?;
+
+    run(&["compile-commands"].to_vec());
+    let compile_commands = fs::read_to_string(COMPILE_COMMANDS_FILE)?;
+    assert_eq!(compile_commands.matches("\"file\": ").count(), TOTAL_SOURCE_FILES);
+    let build = Build::new();
+    let main_file = get_source_file("main.c");
+    let main_args = compiler::object_file_command(&build, &main_file).get_args()
+                                                                     .map(|arg| json_string(arg.to_str().unwrap()))
+                                                                     .collect::<Vec<String>>();
+    assert!(compile_commands.contains(&main_args.join(", ")));
+    assert!(compile_commands.contains(&format!("\"file\": {}", json_string(&main_file))));
+    assert!(compile_commands.contains(&format!("\"output\": {}", json_string(&format!("{}/src/main.c.o", dirs().obj)))));
+    assert!(compile_commands.contains(&format!("\"directory\": {}", json_string(env::current_dir()?.to_str().unwrap()))));
+    // it is only written on demand by default
+    fs::remove_file(COMPILE_COMMANDS_FILE)?;
+    run(&[].to_vec());
+    assert!(!Path::new(COMPILE_COMMANDS_FILE).exists());
+
+    // every source file is listed, even if none of them was recompiled
+    let mut build = Build::new();
+    build.misc.compile_commands = Some(true);
+    write_build(build)?;
+    run(&[].to_vec());
+    assert!(targets_up_to_date(&Build::new()));
+    assert_eq!(fs::read_to_string(COMPILE_COMMANDS_FILE)?.matches("\"file\": ").count(), TOTAL_SOURCE_FILES);
+
+    Ok(())
+}
+
+fn test_clean(settings : &Settings) -> Result<(), Box<dyn std::error::Error>>
+{
+    initialize_project(true, false, settings)?;
+    run(&[].to_vec());
+    run(&["--release"].to_vec());
+
+    let dev = BuildDirectories::new(DEV_PROFILE);
+    let release = BuildDirectories::new(RELEASE_PROFILE);
+    let main_file = get_source_file("main.c");
+    let object_file = |dirs : &BuildDirectories| to_output_file(Path::new(&main_file), &dirs.obj, "o");
+
+    // a dry run only lists what would be removed
+    let output = run(&["clean", "--dry-run", main_file.as_str()].to_vec());
+    assert!(output.contains(&object_file(&dev)) && output.contains(&object_file(&release)));
+    assert!(output.contains("Would remove 4 files"));
+    assert!(Path::new(&object_file(&dev)).exists());
+
+    // the artifacts of a single source file, in a single profile
+    assert!(run(&["clean", "-p", DEV_PROFILE, main_file.as_str()].to_vec()).contains("Removed 2 files, reclaimed "));
+    assert!(!Path::new(&object_file(&dev)).exists());
+    assert!(!Path::new(&to_output_file(Path::new(&main_file), &dev.deps, "d")).exists());
+    assert!(Path::new(&object_file(&release)).exists());
+    assert_eq!(count_files(&dev.obj), TOTAL_SOURCE_FILES - 1);
+
+    // the object files of every profile, while the dependency files stay
+    run(&["clean", "--objects"].to_vec());
+    assert!(!Path::new(&dev.obj).exists() && !Path::new(&release.obj).exists());
+    assert!(Path::new(&release.deps).exists());
+
+    run(&["clean", "--bin"].to_vec());
+    assert!(!Path::new(TEST_PACKAGE_NAME).exists());
+    assert!(Path::new(BUILD_TABLE_DIRECTORY).exists());
+
+    // a profile on its own is removed as a whole
+    run(&["clean", "--release"].to_vec());
+    assert!(!Path::new(&release.root).exists() && Path::new(&dev.root).exists());
+
+    run(&[].to_vec());
+    assert!(Path::new(TEST_PACKAGE_NAME).exists());
+    // cleaning does not need the compiler, so it works even if it is not installed
+    let mut build = Build::new();
+    build.compiler.compiler = "quikc-missing-compiler".to_string();
+    build.compiler.c_compiler = None;
+    build.compiler.cpp_compiler = None;
+    write_build(build)?;
+    run(&["clean", "-qq"].to_vec());
+    assert!(!Path::new(BUILD_TABLE_DIRECTORY).exists() && !Path::new(TEST_PACKAGE_NAME).exists());
+    assert!(Path::new(&main_file).exists());
+
+    // ...and it does not create anything, not even the build directory
+    assert!(run(&["clean", "--dry-run"].to_vec()).contains("Would remove 0 files"));
+    assert!(!Path::new(BUILD_TABLE_DIRECTORY).exists());
+
+    Ok(())
+}
+
+/// Writes `build` to the build configuration file. The standards are removed since
+/// 'Build::new()' prepends '-std=' to them.
+fn write_build(mut build : Build) -> Result<(), Box<dyn std::error::Error>>
+{
+    build.compiler.cstd = None;
+    build.compiler.cppstd = None;
+    write_to_config(&build)
+}
+
+/// Tests if the source directories, include directories, globs and exclusions
+/// in the '[sources]' section are honored.
+fn test_source_configuration(settings : &Settings) -> Result<(), Box<dyn std::error::Error>>
+{
+    initialize_project(true, false, settings)?;
+    for dir in ["lib", "platform/linux", "third_party/include", "third_party/skip"] {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write("third_party/include/tp.h", "int tp(void);\n")?;
+    fs::write("lib/legacy.c", "#include \"tp.h\"\nint tp(void) { return 0; }\n")?;
+    fs::write("platform/linux/plat.c", "int plat(void) { return 0; }\n")?;
+    // neither of these compile, so they must be excluded
+    fs::write("third_party/skip/bad.c", "not valid c\n")?;
+    fs::write(get_source_file("hi_test.c"), "not valid c\n")?;
+
+    let mut build = Build::new();
+    // 'platform' overlaps with './platform/linux', so its files must only be listed once
+    build.sources.dirs = Some(vec!["src".to_string(), "lib".to_string(), "./platform/linux".to_string(), "platform".to_string(), "third_party".to_string()]);
+    build.sources.include_dirs = Some(vec!["include".to_string(), "third_party/include".to_string()]);
+    build.sources.exclude = Some(vec!["third_party/skip".to_string(), "**/*_test.c".to_string()]);
+    write_build(build)?;
+
+    const TOTAL_FILES : usize = TOTAL_SOURCE_FILES + 2;
+    {
+        let mut tools = Tools::new();
+        assert!(tools.build_config.include_flags().contains(&"-I./third_party/include".to_string()));
+        get_src_files(&mut tools);
+
+        assert_eq!(tools.source_files.len(), TOTAL_FILES);
+        assert!(tools.source_files.contains(&"./lib/legacy.c".to_string()));
+        assert!(compile_to_object_files(&tools.source_files, &tools.build_config));
+        assert!(Path::new(&format!("{}/platform/linux/plat.c.o", dirs().obj)).is_file());
+        assert!(link_files(&tools.build_config));
+    }
+
+    // Headers in the extra include directories are tracked as well
+    modify_file("third_party/include/tp.h")?;
+    {
+        let mut tools = Tools::new();
+        get_src_files(&mut tools);
+
+        assert_eq!(tools.source_files, vec!["./lib/legacy.c".to_string()]);
+        assert!(compile_to_object_files(&tools.source_files, &tools.build_config));
+    }
+
+    // Files that no longer match the globs are removed from the build
+    let mut build = Build::new();
+    build.sources.globs = Some(vec!["src/*".to_string(), "platform/**/*.c".to_string()]);
+    write_build(build)?;
+
+    let mut tools = Tools::new();
+    get_src_files(&mut tools);
+
+    assert_eq!(tools.source_files.len(), TOTAL_FILES - 1);
+    assert!(!Path::new(&format!("{}/lib/legacy.c.o", dirs().obj)).exists());
+
+    Ok(())
+}
+
+/// Tests if every profile is built in its own directory, so that switching between
+/// profiles does not recompile anything, and if custom profiles inherit their settings.
+fn test_profiles(settings : &Settings) -> Result<(), Box<dyn std::error::Error>>
+{
+    initialize_project(true, false, settings)?;
+
+    let mut build = Build::new();
+    build.profile = Some(HashMap::from([("fast".to_string(), Profile {
+        inherits : Some(RELEASE_PROFILE.to_string()),
+        optimization_level : Some(OptimizationLevel::Size),
+        defines : Some(vec!["FAST".to_string()]),
+        ..Default::default()
+    })]));
+    write_build(build)?;
+
+    {
+        let mut tools = Tools::new();
+        assert_eq!(tools.build_config.active_profile.name, DEV_PROFILE);
+        get_src_files(&mut tools);
+        assert!(compile_to_object_files(&tools.source_files, &tools.build_config));
+        assert!(link_files(&tools.build_config));
+    }
+
+    // Switching to the release profile builds everything again in its own directory
+    let mut build = Build::new();
+    build.package.debug_build = false;
+    write_build(build)?;
+    {
+        let mut tools = Tools::new();
+        assert_eq!(tools.build_config.dirs, BuildDirectories::new(RELEASE_PROFILE));
+        assert!(tools.build_config.execute_compiler_with_build_info("test.c").get_args().any(|arg| arg == "-DNDEBUG"));
+        get_src_files(&mut tools);
+
+        assert_eq!(tools.source_files.len(), TOTAL_SOURCE_FILES);
+        assert!(compile_to_object_files(&tools.source_files, &tools.build_config));
+        assert!(link_files(&tools.build_config));
+        assert_eq!(count_files(&tools.build_config.dirs.obj), TOTAL_SOURCE_FILES);
+    }
+
+    // ...while the objects of the dev profile are left untouched. The targets
+    // have to be relinked though, since the release build replaced them
+    let mut build = Build::new();
+    build.package.debug_build = true;
+    write_build(build)?;
+    {
+        let mut tools = Tools::new();
+        get_src_files(&mut tools);
+
+        assert!(tools.source_files.is_empty());
+        assert!(!targets_up_to_date(&tools.build_config));
+        assert!(link_files(&tools.build_config));
+        assert!(targets_up_to_date(&tools.build_config));
+    }
+
+    // Custom profiles override the settings they inherit
+    let build = Build::new();
+    let fast = resolve_profile("fast", build.profile.as_ref().unwrap(), &mut Vec::new());
+    assert!(!fast.debug_info);
+    assert_eq!(fast.optimization_level, Some(OptimizationLevel::Size));
+    assert_eq!(fast.defines, vec!["FAST".to_string()]);
+
+    assert!(run(&["--profile", "fast"].to_vec()).contains("[fast build]"));
+    assert_eq!(count_files(&BuildDirectories::new("fast").obj), TOTAL_SOURCE_FILES);
+
+    Ok(())
+}
+
 /// Tests if the project will recompile correctly after a dependency has been moved/deleted.
 fn test_compilation_after_dependency_deletion(settings : &Settings) -> Result<(), Box<dyn std::error::Error>>
 {
@@ -440,7 +1310,7 @@ fn test_compilation_after_dependency_deletion(settings : &Settings) -> Result<()
     // 2 source files had the dependency, with the dependency removed, they were changed, so
     // they need to be recompiled
     assert_eq!(tools.source_files.len(), 2);
-    assert_eq!(fs::read_dir(BUILD_TABLE_OBJECT_FILE_DIRECTORY)?.count(), TOTAL_SOURCE_FILES);
+    assert_eq!(count_files(&dirs().obj), TOTAL_SOURCE_FILES);
 
     let link_success = link_files(&tools.build_config);
     assert!(link_success);
@@ -484,9 +1354,11 @@ fn test_config(settings : &Settings) -> Result<(), Box<dyn std::error::Error>>
     build.package.name = "test".to_string();
     build.package.debug_build = false;
     build.compiler.compiler = "clang++".to_string();
+    build.compiler.c_compiler = None;
+    build.compiler.cpp_compiler = None;
     build.compiler.cstd = Some("c11".to_string());
     build.compiler.cppstd = Some("c++98".to_string());
-    build.misc.optimization_level = Some(3);
+    build.misc.optimization_level = Some(OptimizationLevel::Aggressive);
     build.misc.static_analysis_enabled = Some(true);
     build.compiler.args = Some(vec!["-Wall".to_string(), "-Wextra".to_string()]);
     build.linker.args = Some(vec!["-s".to_string(), "-flto".to_string()]);
@@ -507,9 +1379,11 @@ fn test_config(settings : &Settings) -> Result<(), Box<dyn std::error::Error>>
     assert_eq!(build.package.name, "test");
     assert!(!build.package.debug_build);
     assert_eq!(build.compiler.compiler, "clang++");
+    assert_eq!(build.c_compiler(), "clang");
+    assert_eq!(build.cpp_compiler(), "clang++");
     assert_eq!(build.compiler.cstd.unwrap(), "-std=c11");
     assert_eq!(build.compiler.cppstd.unwrap(), "-std=c++98");
-    assert_eq!(build.misc.optimization_level.unwrap(), 3);
+    assert_eq!(build.misc.optimization_level.unwrap(), OptimizationLevel::Aggressive);
     assert!(build.misc.static_analysis_enabled.unwrap());
     assert_eq!(build.compiler.args.unwrap().len(), 2);
     assert_eq!(build.linker.args.unwrap().len(), 2);
@@ -532,7 +1406,7 @@ fn test_execute_linker_with_build_info(settings : &Settings) -> Result<(), Box<d
 {
     test_first_time_compilation(settings)?;
     let build = Build::new();
-    let cmd = build.execute_linker_with_build_info();
+    let cmd = build.execute_linker_with_build_info(&build.targets[0], &[]);
     let args = cmd.get_args();
 
     // default build configuration should have no arguments or libraries passed to it
@@ -544,7 +1418,7 @@ fn test_execute_linker_with_build_info(settings : &Settings) -> Result<(), Box<d
     let mut build = Build::new();
     build.linker.args = Some(linker_args.clone());
     build.linker.libraries = Some(library_args.clone());
-    let cmd = build.execute_linker_with_build_info();
+    let cmd = build.execute_linker_with_build_info(&build.targets[0], &[]);
     let mut args = cmd.get_args();
     assert_eq!(args.len(), linker_args.len() + library_args.len());
 
@@ -556,40 +1430,263 @@ fn test_execute_linker_with_build_info(settings : &Settings) -> Result<(), Box<d
     }
 
     build.linker.append_args = Some(false);
-    let cmd = build.execute_linker_with_build_info();
+    let cmd = build.execute_linker_with_build_info(&build.targets[0], &[]);
     let args = cmd.get_args();
     // With append args set to false, the linker arguments should still be the same
     assert_eq!(args.len(), linker_args.len() + library_args.len());
 
     build.linker.append_args = Some(true);
-    let cmd = build.execute_linker_with_build_info();
+    let cmd = build.execute_linker_with_build_info(&build.targets[0], &[]);
     let args = cmd.get_args();
 
     // With append args set to true, the linker arguments should be the same as before
     assert_eq!(args.len(), linker_args.len() + library_args.len());
 
-    build.package.debug_build = false;
-    let cmd = build.execute_linker_with_build_info();
+    build.active_profile.debug_info = false;
+    let cmd = build.execute_linker_with_build_info(&build.targets[0], &[]);
     let args = cmd.get_args();
 
     // debug build set to false so should apply the optimization options
-    assert_eq!(args.len(), linker_args.len() + library_args.len() + GCC_AND_CLANG_LINKER_OPTIONS.len());
+    assert_eq!(args.len(), linker_args.len() + library_args.len() + GCC_AND_CLANG_LINKER_OPTIONS.len() + RELEASE_OPTIMIZATION_OPTIONS.len());
 
     build.linker.args = Some(vec![]);
-    let cmd = build.execute_linker_with_build_info();
+    let cmd = build.execute_linker_with_build_info(&build.targets[0], &[]);
     let args = cmd.get_args();
 
-    assert_eq!(args.len(), library_args.len() + GCC_AND_CLANG_LINKER_OPTIONS.len());
+    assert_eq!(args.len(), library_args.len() + GCC_AND_CLANG_LINKER_OPTIONS.len() + RELEASE_OPTIMIZATION_OPTIONS.len());
+
+    // The objects are compiled and linked with the same optimization settings
+    build.misc.optimization_level = Some(OptimizationLevel::Size);
+    build.misc.lto = Some(LtoMode::Auto);
+    let lto_flag = if settings.use_clang {"-flto=thin"} else {"-flto=auto"};
+    let compiler_cmd = build.execute_compiler_with_build_info("test.c");
+    let linker_cmd = build.execute_linker_with_build_info(&build.targets[0], &[]);
+    for cmd in [&compiler_cmd, &linker_cmd] {
+        let args = cmd.get_args().map(|arg| arg.to_str().unwrap()).collect::<Vec<&str>>();
+        assert!(args.contains(&"-Os") && args.contains(&lto_flag), "At line {}: {:?}", line!(), args);
+        assert!(!args.contains(&"-O2") && !args.contains(&"-flto"), "At line {}: {:?}", line!(), args);
+    }
+
+    // ...which also holds if the linker arguments replace the default ones
+    build.linker.args = Some(vec!["-s".to_string()]);
+    build.linker.append_args = Some(false);
+    let args = build.execute_linker_with_build_info(&build.targets[0], &[]).get_args()
+                                                                       .map(|arg| arg.to_str().unwrap().to_string())
+                                                                       .collect::<Vec<String>>();
+    assert!(args.contains(&"-Os".to_string()) && args.contains(&lto_flag.to_string()), "{:?}", args);
+    assert!(args.contains(&"-s".to_string()), "{:?}", args);
+
+    Ok(())
+}
+
+/// Tests if every '[[bin]]' target is linked with the shared object files
+/// and its own entry point.
+fn test_multiple_targets(settings : &Settings) -> Result<(), Box<dyn std::error::Error>>
+{
+    initialize_project(true, false, settings)?;
+    fs::write(get_source_file("tool.c"), "int g(void);\n\nint main(void)\n{\n    return g();\n}\n")?;
+    fs::write(get_source_file("helper.c"), "int helper(void);\nint helper(void) { return 1; }\n")?;
+
+    let mut config = fs::OpenOptions::new().append(true).open(BUILD_CONFIG_FILE)?;
+    config.write_all(b"\n[[bin]]\n    name = \"main\"\n    main = \"./src/main.c\"\n\
+                        \n[[bin]]\n    name = \"tool\"\n    main = \"./src/tool.c\"\n\
+                        \n[[lib]]\n    name = \"helper\"\n    sources = [\"./src/helper.c\"]\n")?;
+
+    let mut tools = Tools::new();
+    get_src_files(&mut tools);
+
+    assert_eq!(tools.build_config.targets.len(), 3);
+    assert_eq!(tools.build_config.targets[2].kind(), TargetKind::Static);
+    assert_eq!(tools.source_files.len(), TOTAL_SOURCE_FILES + 2);
+
+    let compilation_success = compile_to_object_files(&tools.source_files, &tools.build_config);
+    assert!(compilation_success);
+
+    // each entry point is only linked into its own target, otherwise 'main' would be defined twice
+    let link_success = link_files(&tools.build_config);
+    assert!(link_success);
+    assert!(Path::new(TEST_PACKAGE_NAME).is_file());
+    assert!(Path::new("tool").is_file());
+    // the library is built from its own sources, next to the executables
+    assert!(Path::new("libhelper.a").is_file());
+
+    Ok(())
+}
+
+/// Tests if 'quikc run' runs the selected executable with the arguments, working
+/// directory and environment variables it is given, and exits with its status.
+fn test_run(settings : &Settings) -> Result<(), Box<dyn std::error::Error>>
+{
+    initialize_project(false, false, settings)?;
+    fs::write(get_source_file("main.c"), r#"#define _POSIX_C_SOURCE 200809L
+#include <stdio.h>
+#include <stdlib.h>
+#include <unistd.h>
+
+int main(int argc, char **argv)
+{
+    char cwd[4096];
+    const char *greeting = getenv("GREETING");
+
+    for (int i = 1; i < argc; i++)
+        printf("%s\n", argv[i]);
+    printf("%s\n%s\n", greeting ? greeting : "unset", getcwd(cwd, sizeof(cwd)));
+    return argc;
+}
+"#)?;
+
+    // the arguments after '--' are passed as they are, and the exit status is the executable's
+    let (status, output) = run_with_status(&["run", "-qq", "--", "a", "--b"]);
+    assert_eq!(status, Some(3));
+    let project_dir = env::current_dir()?;
+    assert_eq!(output, format!("a\n--b\nunset\n{}\n", project_dir.display()));
+
+    fs::create_dir("./data")?;
+    fs::write(get_source_file("tool.c"), "int main(void)\n{\n    return 7;\n}\n")?;
+    let mut config = fs::OpenOptions::new().append(true).open(BUILD_CONFIG_FILE)?;
+    config.write_all(b"\n[run]\n    working_dir = \"data\"\n    env = { GREETING = \"hi\" }\n\
+                        \n[[bin]]\n    name = \"main\"\n    main = \"./src/main.c\"\n\
+                        \n[[bin]]\n    name = \"tool\"\n    main = \"./src/tool.c\"\n")?;
+
+    // with several executables, one has to be chosen
+    assert_eq!(run_with_status(&["run", "-qq"]), (Some(1), String::new()));
+    assert_eq!(run_with_status(&["run", "-qq", "--target", "tool"]), (Some(7), String::new()));
+    let (status, output) = run_with_status(&["run", "-qq", "--target", "main", "--", "x"]);
+    assert_eq!(status, Some(2));
+    assert_eq!(output, format!("x\nhi\n{}\n", project_dir.join("data").display()));
+
+    let build_config = fs::read_to_string(BUILD_CONFIG_FILE)?.replace("[run]\n", "[run]\n    target = \"tool\"\n");
+    fs::write(BUILD_CONFIG_FILE, build_config)?;
+    assert_eq!(run_with_status(&["run", "-qq"]).0, Some(7));
+
+    Ok(())
+}
+
+/// Tests if a package with the 'static' kind is archived into a static library
+/// instead of being linked into an executable.
+fn test_static_library(settings : &Settings) -> Result<(), Box<dyn std::error::Error>>
+{
+    initialize_project(true, false, settings)?;
+
+    // the standards are stored with their '-std=' prefix, so they cannot be written back as is
+    let mut build = Build::new();
+    build.package.kind = Some(TargetKind::Static);
+    build.archiver.args = Some(vec!["rcs".to_string()]);
+    build.compiler.cstd = None;
+    build.compiler.cppstd = None;
+    write_to_config(&build)?;
+
+    // an '[archiver]' section may only set the arguments, 'ar' is used then
+    let build_config = fs::read_to_string(BUILD_CONFIG_FILE)?;
+    assert!(build_config.contains("archiver = \"ar\"\n"));
+    fs::write(BUILD_CONFIG_FILE, build_config.replace("archiver = \"ar\"\n", ""))?;
+
+    let mut tools = Tools::new();
+    get_src_files(&mut tools);
+
+    assert_eq!(tools.build_config.archiver.archiver, "ar");
+    assert_eq!(tools.build_config.targets[0].output_file(), format!("lib{TEST_PACKAGE_NAME}.a"));
+
+    let compilation_success = compile_to_object_files(&tools.source_files, &tools.build_config);
+    assert!(compilation_success);
+
+    let link_success = link_files(&tools.build_config);
+    assert!(link_success);
+    assert!(Path::new(&format!("lib{TEST_PACKAGE_NAME}.a")).is_file());
+    assert!(!Path::new(TEST_PACKAGE_NAME).exists());
+
+    Ok(())
+}
+
+/// Tests if a shared library is linked with its soname, has its symbolic
+/// link chain created and only exports the symbols in its export list.
+fn test_shared_library(settings : &Settings) -> Result<(), Box<dyn std::error::Error>>
+{
+    initialize_project(true, false, settings)?;
+
+    let mut build = Build::new();
+    build.package.kind = Some(TargetKind::Shared);
+    build.package.version = Some("1.2.3".to_string());
+    build.package.exports = Some(vec!["hi".to_string()]);
+    build.compiler.cstd = None;
+    build.compiler.cppstd = None;
+    write_to_config(&build)?;
+    // an export list alone does not change how the symbols are compiled...
+    let args = Build::new().execute_compiler_with_build_info("test.c").get_args()
+                           .map(|s| s.to_str().unwrap().to_string())
+                           .collect::<Vec<String>>();
+    assert!(args.contains(&"-fPIC".to_string()) && !args.contains(&"-fvisibility=hidden".to_string()));
+
+    // ...while with hidden visibility, every symbol is hidden when it is compiled, unless
+    // it is declared with the export macro
+    let mut build = Build::new();
+    build.package.visibility = Some(Visibility::Hidden);
+    build.compiler.cstd = None;
+    build.compiler.cppstd = None;
+    write_to_config(&build)?;
+    let hi_source = fs::read_to_string(get_source_file("hi.c"))?.replace("void hi(", "QUIKC_EXPORT void hi(");
+    fs::write(get_source_file("hi.c"), hi_source)?;
+
+    let mut tools = Tools::new();
+    get_src_files(&mut tools);
+
+    let target = &tools.build_config.targets[0];
+    assert_eq!(target.output_file(), format!("lib{TEST_PACKAGE_NAME}.so.1.2"));
+    assert_eq!(target.soname(), format!("lib{TEST_PACKAGE_NAME}.so.1"));
+
+    let args = tools.build_config.execute_compiler_with_build_info("test.c").get_args()
+                                 .map(|s| s.to_str().unwrap().to_string())
+                                 .collect::<Vec<String>>();
+    assert!(args.contains(&"-fPIC".to_string()));
+    assert!(args.contains(&"-fvisibility=hidden".to_string()));
+    assert!(!args.contains(&"-fvisibility-inlines-hidden".to_string()));
+    let cpp_args = tools.build_config.execute_compiler_with_build_info("test.cpp").get_args()
+                                     .map(|s| s.to_str().unwrap().to_string())
+                                     .collect::<Vec<String>>();
+    assert!(cpp_args.contains(&"-fvisibility-inlines-hidden".to_string()));
+
+    let compilation_success = compile_to_object_files(&tools.source_files, &tools.build_config);
+    assert!(compilation_success);
+
+    let link_success = link_files(&tools.build_config);
+    assert!(link_success);
+
+    assert!(Path::new(&format!("lib{TEST_PACKAGE_NAME}.so.1.2")).is_file());
+    assert_eq!(fs::read_link(format!("lib{TEST_PACKAGE_NAME}.so.1"))?.to_str().unwrap(), format!("lib{TEST_PACKAGE_NAME}.so.1.2"));
+    assert_eq!(fs::read_link(format!("lib{TEST_PACKAGE_NAME}.so"))?.to_str().unwrap(), format!("lib{TEST_PACKAGE_NAME}.so.1"));
+
+    // 'g' is defined in 'dep.c' but is not in the export list
+    let symbols = Command::new("nm").args(["-D", "--defined-only"])
+                                    .arg(format!("lib{TEST_PACKAGE_NAME}.so.1.2"))
+                                    .output()?.stdout;
+    let symbols = String::from_utf8_lossy(&symbols);
+    assert!(symbols.lines().any(|line| line.ends_with(" hi")));
+    assert!(!symbols.lines().any(|line| line.ends_with(" g")));
+    // 'g' already has hidden visibility in its object file, not only after linking
+    let dep_object = to_output_file(Path::new(&get_source_file("dep.c")), &dirs().obj, "o");
+    let dep_symbols = Command::new("readelf").args(["-s", "-W"]).arg(dep_object).output()?.stdout;
+    assert!(String::from_utf8_lossy(&dep_symbols).lines().any(|line| line.ends_with(" g") && line.contains(" HIDDEN ")));
 
     Ok(())
 }
 
+/// Runs quikc with `args`, returning its exit status along with its output.
+fn run_with_status(args : &[&str]) -> (Option<i32>, String)
+{
+    let mut cmd = Command::new("cargo");
+    cmd.arg("run");
+    #[cfg(feature = "quikc-nightly")]
+        cmd.args(["--features", "quikc-nightly"]);
+    let output = cmd.arg("--").args(args).output().unwrap();
+    (output.status.code(), String::from_utf8_lossy(&output.stdout).to_string())
+}
+
 fn run(args : &Vec<&str>) -> String
 {
     #[cfg(not(feature = "quikc-nightly"))]
     {
         let cmd = Command::new("cargo")
-                        .args(["run", ".."])
+                        .args(["run", "--"])
                         .args(args)
                         .output()
                         .unwrap()
@@ -599,7 +1696,7 @@ fn run(args : &Vec<&str>) -> String
     #[cfg(feature = "quikc-nightly")]
     {
         let cmd = Command::new("cargo")
-                        .args(["run", "--features", "quikc-nightly", ".."])
+                        .args(["run", "--features", "quikc-nightly", "--"])
                         .args(args)
                         .output()
                         .unwrap()
@@ -614,41 +1711,43 @@ fn test_cmdline_flags(settings : &Settings) -> Result<(), Box<dyn std::error::Er
 
     // Test if the version flag works
     #[cfg(not(feature = "quikc-nightly"))]
-        assert_eq!(run(&["-v"].to_vec()), concatcp!("quikc v", VERSIONS[0], "\n"));
+        assert_eq!(run(&["--version"].to_vec()), concatcp!("quikc v", VERSIONS[0], "\n"));
     #[cfg(feature = "quikc-nightly")]
-        assert_eq!(run(&["-v"].to_vec()), concatcp!("quikc-nightly v", NIGHTLY_VERSION, "\n"));
+        assert_eq!(run(&["--version"].to_vec()), concatcp!("quikc-nightly v", NIGHTLY_VERSION, "\n"));
 
-    // Test if -h works (disables verbose output)
+    // Test if '--quiet' works (disables verbose output)
     let mut build = Build::new();
 
     // a compiler unknown to quikc should give a warning/note that the compiler is unknown
     // so default configuration settings cannot be used
     build.compiler.compiler = "not_a_real_compiler".to_string();
+    build.compiler.c_compiler = None;
+    build.compiler.cpp_compiler = None;
 
     write_to_config(&build)?;
-    assert!(!run(&["-h"].to_vec()).contains("cannot use default configuration because compiler vendor is unknown, please supply your own flags."));
+    assert!(!run(&["--quiet"].to_vec()).contains("cannot use default configuration because compiler vendor is unknown, please supply your own flags."));
 
-    // '-hh' disables all output, except for compiler messages
-    assert_eq!(run(&["-hh"].to_vec()), "");
+    // '-qq' disables all output, except for compiler messages
+    assert_eq!(run(&["build", "-qq"].to_vec()), "");
 
     reset()?;
     test_first_time_compilation(settings)?;
 
-    // '-c' does not link the object files
-    assert!(run(&["-c"].to_vec()).contains("Successfully compiled source files to object files"));
+    // 'check' does not link the object files
+    assert!(run(&["check"].to_vec()).contains("Successfully compiled source files to object files"));
 
-    // '-S' with no other arguments assembles all of the source files
+    // 'asm' with no other arguments assembles all of the source files
     reset()?;
     initialize_project(true, false, settings)?;
-    run(&["-S"].to_vec());
+    run(&["asm"].to_vec());
 
-    assert_eq!(fs::read_dir(BUILD_TABLE_ASM_DIRECTORY)?.count(), TOTAL_SOURCE_FILES);
+    assert_eq!(count_files(&dirs().asm), TOTAL_SOURCE_FILES);
     
     reset()?;
     initialize_project(true, false, settings)?;
-    run(&["-S", "./src/main.c"].to_vec());
+    run(&["asm", "./src/main.c"].to_vec());
 
-    assert_eq!(fs::read_dir(BUILD_TABLE_ASM_DIRECTORY)?.count(), 1);
+    assert_eq!(count_files(&dirs().asm), 1);
 
     Ok(())
 }
@@ -712,7 +1811,7 @@ fn test_execute_compiler_with_build_info(settings : &Settings) -> Result<(), Box
     }
 
     let mut build = Build::new();
-    build.package.debug_build = false;
+    build.active_profile.debug_info = false;
 
     // Check if the default configuration for C on release builds is correct
     let command = build.execute_compiler_with_build_info("test.c");
@@ -721,7 +1820,7 @@ fn test_execute_compiler_with_build_info(settings : &Settings) -> Result<(), Box
     if settings.use_clang {
         let expected = vec![DEFAULT_C_STANDARD].into_iter()
                                                     .chain(CLANG_COMPILER_NONEXCLUSIVE_WARNINGS).collect::<Vec<&str>>().into_iter()
-                                                    .chain(GCC_AND_CLANG_OPTIMIZATION_OPTIONS).collect::<Vec<&str>>().into_iter()
+                                                    .chain(RELEASE_OPTIMIZATION_OPTIONS).collect::<Vec<&str>>().into_iter()
                                                     .chain(GCC_AND_CLANG_DIALECT_OPTIONS).collect::<Vec<&str>>();
 
         for arg in &expected {
@@ -733,7 +1832,7 @@ fn test_execute_compiler_with_build_info(settings : &Settings) -> Result<(), Box
         let expected = vec![DEFAULT_C_STANDARD].into_iter()
                                                     .chain(GCC_COMPILER_NONEXCLUSIVE_WARNINGS).collect::<Vec<&str>>().into_iter()
                                                     .chain(GCC_AND_CLANG_DIALECT_OPTIONS).collect::<Vec<&str>>().into_iter()
-                                                    .chain(GCC_AND_CLANG_OPTIMIZATION_OPTIONS).collect::<Vec<&str>>().into_iter()
+                                                    .chain(RELEASE_OPTIMIZATION_OPTIONS).collect::<Vec<&str>>().into_iter()
                                                     .chain(GCC_COMPILER_C_EXCLUSIVE_WARNINGS).collect::<Vec<&str>>();
 
         for arg in &expected {
@@ -750,7 +1849,7 @@ fn test_execute_compiler_with_build_info(settings : &Settings) -> Result<(), Box
                                                     .chain(CLANG_COMPILER_NONEXCLUSIVE_WARNINGS).collect::<Vec<&str>>().into_iter()
                                                     .chain(GCC_AND_CLANG_DIALECT_OPTIONS).collect::<Vec<&str>>().into_iter()
                                                     .chain(CLANG_COMPILER_CPP_WARNINGS).collect::<Vec<&str>>().into_iter()
-                                                    .chain(GCC_AND_CLANG_OPTIMIZATION_OPTIONS).collect::<Vec<&str>>().into_iter()
+                                                    .chain(RELEASE_OPTIMIZATION_OPTIONS).collect::<Vec<&str>>().into_iter()
                                                     .chain(GCC_AND_CLANG_CPP_DIALECT_OPTIONS).collect::<Vec<&str>>();
 
         for arg in &expected {
@@ -762,7 +1861,7 @@ fn test_execute_compiler_with_build_info(settings : &Settings) -> Result<(), Box
                                                     .chain(GCC_COMPILER_NONEXCLUSIVE_WARNINGS).collect::<Vec<&str>>().into_iter()
                                                     .chain(GCC_AND_CLANG_DIALECT_OPTIONS).into_iter()
                                                     .chain(GCC_COMPILER_CPP_EXCLUSIVE_WARNINGS).collect::<Vec<&str>>().into_iter()
-                                                    .chain(GCC_AND_CLANG_OPTIMIZATION_OPTIONS).collect::<Vec<&str>>().into_iter()
+                                                    .chain(RELEASE_OPTIMIZATION_OPTIONS).collect::<Vec<&str>>().into_iter()
                                                     .chain(GCC_AND_CLANG_CPP_DIALECT_OPTIONS).collect::<Vec<&str>>();
 
         for arg in &expected {
@@ -774,11 +1873,11 @@ fn test_execute_compiler_with_build_info(settings : &Settings) -> Result<(), Box
     let chosen_cpp_standard = "-std=c++14";
     let chosen_c_standard = "-std=c99";
 
-    build.package.debug_build = false;
+    build.active_profile.debug_info = false;
     build.compiler.cppstd = Some(chosen_cpp_standard.to_string());
     build.compiler.cstd = Some(chosen_c_standard.to_string());
 
-    build.misc.optimization_level = Some(3);
+    build.misc.optimization_level = Some(OptimizationLevel::Aggressive);
     build.misc.static_analysis_enabled = Some(true);
 
     // Check if custom configuration rules override the default ones (they should)
@@ -789,7 +1888,7 @@ fn test_execute_compiler_with_build_info(settings : &Settings) -> Result<(), Box
     if settings.use_clang {
         let expected = vec![chosen_c_standard].into_iter()
                                                     .chain(CLANG_COMPILER_NONEXCLUSIVE_WARNINGS).collect::<Vec<&str>>().into_iter()
-                                                    .chain(GCC_AND_CLANG_ENHANCED_OPTIMIZATION_OPTIONS).collect::<Vec<&str>>().into_iter()
+                                                    .chain(AGGRESSIVE_OPTIMIZATION_OPTIONS).collect::<Vec<&str>>().into_iter()
                                                     .chain(GCC_AND_CLANG_DIALECT_OPTIONS).collect::<Vec<&str>>();
 
         for arg in &expected {
@@ -801,7 +1900,7 @@ fn test_execute_compiler_with_build_info(settings : &Settings) -> Result<(), Box
         let expected = vec![chosen_c_standard].into_iter()
                                                     .chain(GCC_COMPILER_NONEXCLUSIVE_WARNINGS).collect::<Vec<&str>>().into_iter()
                                                     .chain(GCC_AND_CLANG_DIALECT_OPTIONS).collect::<Vec<&str>>().into_iter()
-                                                    .chain(GCC_AND_CLANG_ENHANCED_OPTIMIZATION_OPTIONS).collect::<Vec<&str>>().into_iter()
+                                                    .chain(AGGRESSIVE_OPTIMIZATION_OPTIONS).collect::<Vec<&str>>().into_iter()
                                                     .chain(GCC_COMPILER_C_EXCLUSIVE_WARNINGS).collect::<Vec<&str>>();
 
         for arg in &expected {
@@ -819,7 +1918,7 @@ fn test_execute_compiler_with_build_info(settings : &Settings) -> Result<(), Box
                                                     .chain(CLANG_COMPILER_NONEXCLUSIVE_WARNINGS).collect::<Vec<&str>>().into_iter()
                                                     .chain(GCC_AND_CLANG_DIALECT_OPTIONS).collect::<Vec<&str>>().into_iter()
                                                     .chain(CLANG_COMPILER_CPP_WARNINGS).collect::<Vec<&str>>().into_iter()
-                                                    .chain(GCC_AND_CLANG_ENHANCED_OPTIMIZATION_OPTIONS).collect::<Vec<&str>>().into_iter()
+                                                    .chain(AGGRESSIVE_OPTIMIZATION_OPTIONS).collect::<Vec<&str>>().into_iter()
                                                     .chain(GCC_AND_CLANG_CPP_DIALECT_OPTIONS).collect::<Vec<&str>>();
 
         for arg in &expected {
@@ -831,7 +1930,7 @@ fn test_execute_compiler_with_build_info(settings : &Settings) -> Result<(), Box
                                                     .chain(GCC_COMPILER_NONEXCLUSIVE_WARNINGS).collect::<Vec<&str>>().into_iter()
                                                     .chain(GCC_AND_CLANG_DIALECT_OPTIONS).into_iter()
                                                     .chain(GCC_COMPILER_CPP_EXCLUSIVE_WARNINGS).collect::<Vec<&str>>().into_iter()
-                                                    .chain(GCC_AND_CLANG_ENHANCED_OPTIMIZATION_OPTIONS).collect::<Vec<&str>>().into_iter()
+                                                    .chain(AGGRESSIVE_OPTIMIZATION_OPTIONS).collect::<Vec<&str>>().into_iter()
                                                     .chain(GCC_AND_CLANG_CPP_DIALECT_OPTIONS).collect::<Vec<&str>>();
 
         for arg in &expected {
@@ -894,3 +1993,197 @@ fn test_execute_compiler_with_build_info(settings : &Settings) -> Result<(), Box
     Ok(())
 }
 
+
+#[test]
+fn test_parse_gcc_dependency_file()
+{
+    // output of 'gcc -MMD -MP' with escaped spaces, '$' and '#' in the file names
+    let contents = "main.o: src/main.c include/hi.h inc\\ dir/a\\ b.h \\\n inc\\ dir/c$$d\\#e.h\n\
+                    include/hi.h:\n\
+                    inc\\ dir/a\\ b.h:\n\
+                    inc\\ dir/c$$d\\#e.h:\n";
+    let rules = depfile::parse(contents);
+
+    assert_eq!(rules.len(), 4);
+    assert_eq!(rules[0], depfile::Rule {
+        targets : vec!["main.o".to_string()],
+        prerequisites : vec!["src/main.c".to_string(), 
+                             "include/hi.h".to_string(), 
+                             "inc dir/a b.h".to_string(), 
+                             "inc dir/c$d#e.h".to_string()]
+    });
+    assert_eq!(rules[2].targets, vec!["inc dir/a b.h".to_string()]);
+    assert!(rules[1..].iter().all(|rule| rule.prerequisites.is_empty()));
+}
+
+#[test]
+fn test_parse_clang_dependency_file()
+{
+    // clang indents continued lines with two spaces and may use CRLF line endings
+    let contents = "build/my\\ main.o build/main.d: src/main.c \\\r\n  include/hi.h \\\r\n  include/dep.h\r\n";
+    let rules = depfile::parse(contents);
+
+    assert_eq!(rules, vec![depfile::Rule {
+        targets : vec!["build/my main.o".to_string(), "build/main.d".to_string()],
+        prerequisites : vec!["src/main.c".to_string(), "include/hi.h".to_string(), "include/dep.h".to_string()]
+    }]);
+}
+
+#[test]
+fn test_parse_dependency_file_backslashes()
+{
+    // backslashes that do not precede a space are kept as is, and the ones that do are doubled
+    let contents = "a.o: dir\\file.h dir\\\\\\ x.h trailing\\\\ next.h";
+    let rules = depfile::parse(contents);
+
+    assert_eq!(rules[0].prerequisites, vec!["dir\\file.h".to_string(),
+                                            "dir\\ x.h".to_string(),
+                                            "trailing\\".to_string(),
+                                            "next.h".to_string()]);
+}
+
+#[test]
+fn test_parse_optimization_settings()
+{
+    use toml::Value;
+
+    assert_eq!(OptimizationLevel::try_from(Value::Integer(0)), Ok(OptimizationLevel::None));
+    assert_eq!(OptimizationLevel::try_from(Value::String("1".to_string())), Ok(OptimizationLevel::Basic));
+    assert_eq!(OptimizationLevel::try_from(Value::String("z".to_string())), Ok(OptimizationLevel::MinSize));
+    assert_eq!(OptimizationLevel::try_from(Value::String("fast".to_string())).map(OptimizationLevel::flag), Ok("-Ofast"));
+    assert!(OptimizationLevel::try_from(Value::Integer(4)).is_err());
+    assert!(OptimizationLevel::try_from(Value::String("O2".to_string())).is_err());
+    assert!(OptimizationLevel::try_from(Value::Boolean(true)).is_err());
+    assert_eq!(OptimizationLevel::try_from(Value::String("3".to_string())), Ok(OptimizationLevel::Aggressive));
+    assert_eq!(OptimizationLevel::try_from(Value::Integer(2)), Ok(OptimizationLevel::Default));
+    // the integers 1 and 3 used to mean something else, so they are rejected
+    assert!(OptimizationLevel::try_from(Value::Integer(3)).unwrap_err().contains("\"fast\""));
+    assert!(OptimizationLevel::try_from(Value::Integer(1)).unwrap_err().contains("\"1\""));
+
+    assert_eq!(LtoMode::try_from(Value::Boolean(true)), Ok(LtoMode::Full));
+    assert_eq!(LtoMode::try_from(Value::Boolean(false)), Ok(LtoMode::Off));
+    assert_eq!(LtoMode::try_from(Value::String("thin".to_string())), Ok(LtoMode::Thin));
+    assert!(LtoMode::try_from(Value::String("fat".to_string())).is_err());
+    assert!(LtoMode::try_from(Value::Integer(1)).is_err());
+    assert_eq!(LtoMode::Auto.flag(CompilerFamily::Gcc), Some("-flto=auto"));
+    assert_eq!(LtoMode::Auto.flag(CompilerFamily::Clang), Some("-flto=thin"));
+    assert_eq!(LtoMode::Off.flag(CompilerFamily::Gcc), None);
+}
+
+#[test]
+fn test_parse_compiler_version_output()
+{
+    let gcc = "gcc-13 (Ubuntu 13.2.0-4ubuntu3) 13.2.0\nCopyright (C) 2023 Free Software Foundation, Inc.\n";
+    assert_eq!(parse_version_output(gcc), CompilerInfo { family : CompilerFamily::Gcc, version : "13.2.0".to_string() });
+
+    let cross_gcc = "x86_64-linux-gnu-gcc (GCC) 12.1.1 20220730\n";
+    assert_eq!(parse_version_output(cross_gcc), CompilerInfo { family : CompilerFamily::Gcc, version : "12.1.1".to_string() });
+
+    // the date of a snapshot is not taken for the version
+    let arch_gcc = "gcc (GCC) 13.2.1 20230801\nCopyright (C) 2023 Free Software Foundation, Inc.\n";
+    assert_eq!(parse_version_output(arch_gcc).version, "13.2.1");
+
+    let clang = "Ubuntu clang version 14.0.0-1ubuntu1.1\nTarget: x86_64-pc-linux-gnu\nThread model: posix\n";
+    assert_eq!(parse_version_output(clang), CompilerInfo { family : CompilerFamily::Clang, version : "14.0.0".to_string() });
+
+    let apple_clang = "Apple clang version 15.0.0 (clang-1500.0.40.1)\nTarget: arm64-apple-darwin23.0.0\n";
+    assert_eq!(parse_version_output(apple_clang), CompilerInfo { family : CompilerFamily::Clang, version : "15.0.0".to_string() });
+
+    assert_eq!(parse_version_output("tcc version 0.9.27 (x86_64 Linux)\n"), CompilerInfo::default());
+    assert_eq!(parse_version_output(""), CompilerInfo::default());
+}
+
+#[test]
+fn test_json_string()
+{
+    assert_eq!(json_string("-DNAME=\"quikc\""), "\"-DNAME=\\\"quikc\\\"\"");
+    assert_eq!(json_string("C:\\src\tmain.c\n"), "\"C:\\\\src\\tmain.c\\n\"");
+    assert_eq!(json_string("\u{1}"), "\"\\u0001\"");
+}
+
+/// Parses the command line arguments `args`, without the program name.
+fn parse(args : &[&str]) -> Result<cli::Cli, String>
+{
+    cli::parse(&args.iter().map(|arg| arg.to_string()).collect::<Vec<String>>())
+}
+
+#[test]
+fn test_parse_command_line()
+{
+    let cli = parse(&[]).unwrap();
+    assert_eq!((cli.command, cli.command()), (None, Subcommand::Build));
+
+    let cli = parse(&["-qq", "check", "--profile=fast", "-j4"]).unwrap();
+    assert_eq!(cli.command(), Subcommand::Check);
+    assert_eq!(cli.flags, QuikcFlags::HIDE_VERBOSE_OUTPUT|QuikcFlags::HIDE_OUTPUT);
+    assert_eq!((cli.profile.as_deref(), cli.jobs), (Some("fast"), Some(4)));
+
+    let cli = parse(&["--release", "asm", "--jobs", "2", "./src/main.c", "--", "-weird.c"]).unwrap();
+    assert_eq!((cli.command(), cli.profile.as_deref(), cli.jobs), (Subcommand::Asm, Some(RELEASE_PROFILE), Some(2)));
+    assert_eq!(cli.operands, ["./src/main.c", "-weird.c"]);
+
+    let cli = parse(&["help", "config"]).unwrap();
+    assert!(cli.show_help && cli.command == Some(Subcommand::Config));
+
+    assert_eq!(parse(&["biuld"]).unwrap_err(), "unknown command 'biuld', the commands are: build, check, asm, run, config, compile-commands, clean, new, init");
+    assert_eq!(parse(&["new"]).unwrap_err(), "'quikc new' requires an argument: <NAME>");
+    assert_eq!(parse(&["init", "a", "b"]).unwrap_err(), "unexpected argument 'b', 'quikc init' takes only one argument");
+    let cli = parse(&["run", "-r", "--target=tool", "--", "--verbose", "x"]).unwrap();
+    assert_eq!((cli.command(), cli.target.as_deref(), cli.operands.as_slice()), (Subcommand::Run, Some("tool"), ["--verbose".to_string(), "x".to_string()].as_slice()));
+    assert_eq!(parse(&["--fast"]).unwrap_err(), "unknown option '--fast'");
+    assert_eq!(parse(&["-qx"]).unwrap_err(), "unknown option '-x' in '-qx'");
+    assert_eq!(parse(&["-c"]).unwrap_err(), "'-c' was replaced by the 'check' command, run 'quikc check' instead");
+    assert_eq!(parse(&["-S", "test.c"]).unwrap_err(), "'-S' was replaced by the 'asm' command, run 'quikc asm' instead");
+    assert_eq!(parse(&["build", "main.c"]).unwrap_err(), "unexpected argument 'main.c', 'quikc build' does not take any arguments");
+    assert_eq!(parse(&["-j", "0"]).unwrap_err(), "invalid value '0' for '--jobs', expected a number greater than 0");
+    assert_eq!(parse(&["--profile"]).unwrap_err(), "'--profile' requires a value (NAME)");
+    assert_eq!(parse(&["--release=yes"]).unwrap_err(), "'--release' does not take a value");
+    assert_eq!(parse(&["config", "--jobs=2"]).unwrap_err(), "'--jobs' is not an option of 'quikc config'");
+    assert!(parse(&["-r", "-p", "dev"]).is_err());
+    assert!(parse(&["--verbose", "-q"]).is_err());
+    // '-h' and '-v' hid the output and printed the version, so they are not reused
+    assert_eq!(parse(&["-hh"]).unwrap_err(), "'-h' was replaced by '-q' ('--quiet'), and '-hh' by '-qq'");
+    assert_eq!(parse(&["-v"]).unwrap_err(), "'-v' was replaced by '-V' ('--version')");
+
+    // the help is generated from the same definitions
+    assert!(cli::help(None).contains("compile-commands") && cli::help(None).contains("-j, --jobs <N>"));
+    assert!(!cli::help(Some(Subcommand::Config)).contains("--jobs"));
+    assert!(cli::help(Some(Subcommand::Asm)).contains("Usage: quikc asm [OPTIONS] [FILES]..."));
+}
+
+#[test]
+fn test_clean_options()
+{
+    let cli = parse(&["clean", "--objects", "--asm", "-n", "-r"]).unwrap();
+    assert_eq!((cli.clean_scope, cli.dry_run), (CleanScope::OBJECTS|CleanScope::ASM, true));
+    assert_eq!(parse(&["build", "--dry-run"]).unwrap_err(), "'--dry-run' is not an option of 'quikc build'");
+    assert_eq!((human_readable_size(1023), human_readable_size(1536), human_readable_size(5 << 30)), ("1023 B".to_string(), "1.5 KiB".to_string(), "5.0 GiB".to_string()));
+}
+
+#[test]
+fn test_new_command()
+{
+    assert_eq!(parse(&["new", "lib", "-t", "lib"]).unwrap_err(), "invalid value 'lib' for '--template', expected one of: c, cpp, static, shared, embedded, embedded-cpp");
+    assert_eq!(parse(&["new", "app", "--release"]).unwrap_err(), "'--release' is not an option of 'quikc new'");
+    let cli = parse(&["new", "app", "--template=shared", "--git"]).unwrap();
+    assert_eq!((cli.operands.as_slice(), cli.template, cli.git), (["app".to_string()].as_slice(), Some(Template::Shared), true));
+    assert!(project::validate_package_name("my_app-2").is_ok());
+    assert!(project::validate_package_name("my app").is_err() && project::validate_package_name("-app").is_err());
+}
+
+#[test]
+fn test_compiler_counterparts()
+{
+    assert_eq!(compiler::cpp_counterpart("gcc").as_deref(), Some("g++"));
+    assert_eq!(compiler::cpp_counterpart("/usr/bin/gcc-13").as_deref(), Some("/usr/bin/g++-13"));
+    assert_eq!(compiler::cpp_counterpart("x86_64-linux-gnu-gcc").as_deref(), Some("x86_64-linux-gnu-g++"));
+    assert_eq!(compiler::cpp_counterpart("ccache clang-17").as_deref(), Some("ccache clang++-17"));
+    assert_eq!(compiler::cpp_counterpart("cc").as_deref(), Some("c++"));
+    assert_eq!(compiler::cpp_counterpart("g++").as_deref(), Some("g++"));
+    assert_eq!(compiler::cpp_counterpart("tcc"), None);
+
+    assert_eq!(compiler::c_counterpart("g++-13"), "gcc-13");
+    assert_eq!(compiler::c_counterpart("ccache /usr/bin/clang++"), "ccache /usr/bin/clang");
+    assert_eq!(compiler::c_counterpart("c++"), "cc");
+    assert_eq!(compiler::c_counterpart("tcc"), "tcc");
+}
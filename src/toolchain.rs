@@ -0,0 +1,281 @@
+use std::{collections::HashMap, fs::{self, File}, io::Write, path::{Path, PathBuf}, process::Stdio};
+
+use const_format::concatcp;
+use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
+use serde_derive::Deserialize;
+use xxhash_rust::xxh3::xxh3_64;
+
+use crate::{buildtable::{BUILD_TABLE_DIRECTORY, get_duration_since_modified}, compiler};
+
+/// Caches the family and version of every compiler that was detected, so
+/// that the compilers only have to be run once.
+pub const TOOLCHAIN_CACHE_FILE : &str = concatcp!(BUILD_TABLE_DIRECTORY, "/compilers");
+/// Caches whether each compiler version supports the default flags.
+pub const FLAG_CACHE_FILE : &str = concatcp!(BUILD_TABLE_DIRECTORY, "/flags");
+
+// compiled with the flags that are probed, this must not cause any warnings
+const PROBE_SOURCE : &str = "int main(void) { return 0; }\n";
+
+/// The vendor of a compiler, which decides the default configuration it gets.
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum CompilerFamily
+{
+    Gcc,
+    Clang,
+    #[default]
+    Unknown
+}
+
+impl CompilerFamily
+{
+    #[inline]
+    pub fn is_gcc_or_clang(self) -> bool
+    {
+        self != CompilerFamily::Unknown
+    }
+
+    pub fn name(self) -> &'static str
+    {
+        match self {
+            CompilerFamily::Gcc => "gcc",
+            CompilerFamily::Clang => "clang",
+            CompilerFamily::Unknown => "unknown"
+        }
+    }
+
+    fn from_name(name : &str) -> Option<CompilerFamily>
+    {
+        match name {
+            "gcc" => Some(CompilerFamily::Gcc),
+            "clang" => Some(CompilerFamily::Clang),
+            "unknown" => Some(CompilerFamily::Unknown),
+            _ => None
+        }
+    }
+}
+
+/// The family and version of a compiler, as reported by 'compiler --version'.
+#[derive(Deserialize, Clone, PartialEq, Eq, Debug, Default)]
+pub struct CompilerInfo
+{
+    pub family : CompilerFamily,
+    pub version : String
+}
+
+/// Returns the leading 'MAJOR.MINOR.PATCH' part of `word`, e.g. '14.0.0' for '14.0.0-1ubuntu1'.
+fn version_number(word : &str) -> Option<String>
+{
+    let version = word.split(|c : char| !c.is_ascii_digit() && c != '.')
+                      .next()?
+                      .trim_end_matches('.');
+    version.starts_with(|c : char| c.is_ascii_digit()).then(|| version.to_string())
+}
+
+/// Parses the output of 'compiler --version'. Clang identifies itself on
+/// the first line ('clang version 17.0.6', possibly with a vendor prefix),
+/// while gcc (also when invoked as 'cc' or a cross compiler) prints its version
+/// after the package's name on the first line, followed by the Free Software
+/// Foundation's copyright.
+pub fn parse_version_output(output : &str) -> CompilerInfo
+{
+    let first_line = output.lines().next().unwrap_or_default();
+
+    if let Some((_, version)) = first_line.split_once("clang version ") {
+        return CompilerInfo {
+            family : CompilerFamily::Clang,
+            version : version.split_whitespace().next().and_then(version_number).unwrap_or_default()
+        };
+    }
+
+    // the version follows the package's name in parentheses, and may be followed by
+    // the date of a snapshot ('gcc (GCC) 13.2.1 20230801'), which is not a version
+    if output.contains("Free Software Foundation") || first_line.contains("(GCC)") {
+        let after_package = first_line.rsplit_once(')').map_or(first_line, |(_, rest)| rest);
+        return CompilerInfo {
+            family : CompilerFamily::Gcc,
+            version : after_package.split_whitespace()
+                                   .filter_map(version_number)
+                                   .find(|version| version.contains('.'))
+                                   .unwrap_or_default()
+        };
+    }
+
+    CompilerInfo::default()
+}
+
+/// Returns the path of `program`, looking it up in 'PATH' if it is only a name.
+fn find_program(program : &str) -> Option<PathBuf>
+{
+    if program.contains('/') {
+        return Some(PathBuf::from(program)).filter(|path| path.is_file());
+    }
+    std::env::split_paths(&std::env::var_os("PATH")?).map(|dir| dir.join(program))
+                                                     .find(|path| path.is_file())
+}
+
+/// Returns a fingerprint of the programs `compiler` runs (including launchers
+/// such as 'ccache'), which changes when any of them is replaced or updated.
+/// Returns None if one of them can not be found.
+fn compiler_fingerprint(compiler : &str) -> Option<u64>
+{
+    let mut programs = String::new();
+    for program in compiler.split_whitespace().filter(|word| !word.starts_with('-')) {
+        let path = find_program(program)?;
+        let modified = get_duration_since_modified(&fs::metadata(&path).ok()?);
+        programs.push_str(&format!("{}:{modified};", path.display()));
+    }
+    Some(xxh3_64(programs.as_bytes()))
+}
+
+/// Runs 'compiler --version' to find out the compiler's family and version.
+fn run_version_command(compiler : &str) -> CompilerInfo
+{
+    match compiler::compiler_command(compiler).arg("--version").output() {
+        Ok(output) if output.status.success() => parse_version_output(&String::from_utf8_lossy(&output.stdout)),
+        _ => CompilerInfo::default()
+    }
+}
+
+/// Reads the cached compiler information, keyed by the compiler, along with
+/// the fingerprint of the compiler at the time it was detected.
+fn read_toolchain_cache() -> HashMap<String, (u64, CompilerInfo)>
+{
+    let file_contents = fs::read_to_string(TOOLCHAIN_CACHE_FILE).unwrap_or_default();
+
+    file_contents.lines()
+                 .filter_map(|line| {
+                     let (compiler, record) = line.rsplit_once('=')?;
+                     let mut values = record.splitn(3, ':');
+                     let fingerprint = values.next()?.parse().ok()?;
+                     let family = CompilerFamily::from_name(values.next()?)?;
+                     let version = values.next()?.to_string();
+                     Some((compiler.to_string(), (fingerprint, CompilerInfo { family, version })))
+                 })
+                 .collect()
+}
+
+fn write_toolchain_cache(cache : &HashMap<String, (u64, CompilerInfo)>)
+{
+    if !Path::new(BUILD_TABLE_DIRECTORY).exists() {
+        return;
+    }
+    let mut f = File::create(TOOLCHAIN_CACHE_FILE).expect("Failed to create compiler cache file");
+    for (compiler, (fingerprint, info)) in cache {
+        f.write_all(format!("{compiler}={fingerprint}:{}:{}\n", info.family.name(), info.version).as_bytes())
+         .expect("Failed to write to compiler cache file");
+    }
+}
+
+/// Detects the family and version of each of the `compilers`. Compilers are only
+/// run if they were not detected before, or if they changed since they were.
+pub fn detect_compilers(compilers : &[&str]) -> HashMap<String, CompilerInfo>
+{
+    let mut cache = read_toolchain_cache();
+    let mut cache_changed = false;
+    let mut detected = HashMap::new();
+
+    for &compiler in compilers {
+        let fingerprint = compiler_fingerprint(compiler);
+        let info = match (cache.get(compiler), fingerprint) {
+            (Some((cached_fingerprint, info)), Some(fingerprint)) if *cached_fingerprint == fingerprint => info.clone(),
+            (_, Some(fingerprint)) => {
+                let info = run_version_command(compiler);
+                cache.insert(compiler.to_string(), (fingerprint, info.clone()));
+                cache_changed = true;
+                info
+            },
+            // the compiler does not exist, so there is nothing to detect
+            (_, None) => CompilerInfo::default()
+        };
+        detected.insert(compiler.to_string(), info);
+    }
+
+    if cache_changed {
+        write_toolchain_cache(&cache);
+    }
+    detected
+}
+
+/// Returns true if `compiler` accepts every one of the `flags` when compiling
+/// a C (or C++) file. Warnings are turned into errors, since clang only warns
+/// about warning options it does not know.
+fn flags_supported(compiler : &str, is_c_source_file : bool, flags : &[&str]) -> bool
+{
+    let child = compiler::compiler_command(compiler)
+                         .args(["-x", if is_c_source_file {"c"} else {"c++"}, "-fsyntax-only", "-Werror"])
+                         .args(flags)
+                         .arg("-")
+                         .stdin(Stdio::piped())
+                         .stdout(Stdio::null())
+                         .stderr(Stdio::null())
+                         .spawn();
+    let Ok(mut child) = child else {
+        return false;
+    };
+    // the compiler may exit before it reads the source, e.g. if it rejects a flag
+    let _ = child.stdin.take().unwrap().write_all(PROBE_SOURCE.as_bytes());
+    child.wait().is_ok_and(|status| status.success())
+}
+
+/// Reads the cached results of the flag probes. Every line holds the compiler
+/// (and its version), the language, the flag and whether it is supported,
+/// separated by tabs since compilers and flags may contain any other character.
+fn read_flag_cache() -> HashMap<(String, String, String), bool>
+{
+    let file_contents = fs::read_to_string(FLAG_CACHE_FILE).unwrap_or_default();
+
+    file_contents.lines()
+                 .filter_map(|line| {
+                     let mut values = line.split('\t');
+                     let key = (values.next()?.to_string(), values.next()?.to_string(), values.next()?.to_string());
+                     Some((key, values.next()? == "1"))
+                 })
+                 .collect()
+}
+
+fn write_flag_cache(cache : &HashMap<(String, String, String), bool>)
+{
+    if !Path::new(BUILD_TABLE_DIRECTORY).exists() {
+        return;
+    }
+    let mut f = File::create(FLAG_CACHE_FILE).expect("Failed to create flag cache file");
+    for ((compiler, language, flag), supported) in cache {
+        f.write_all(format!("{compiler}\t{language}\t{flag}\t{}\n", u8::from(*supported)).as_bytes())
+         .expect("Failed to write to flag cache file");
+    }
+}
+
+/// Returns the `flags` that `compiler` does not support for source files of the
+/// given language. Each flag is only probed once per compiler version. To keep
+/// this fast, the flags are first probed together and only probed one by one
+/// (in parallel) if the compiler rejects any of them.
+pub fn unsupported_flags(compiler : &str, info : &CompilerInfo, is_c_source_file : bool, flags : &[&'static str]) -> Vec<&'static str>
+{
+    let compiler_version = format!("{compiler}@{}", info.version);
+    let language = if is_c_source_file {"c"} else {"c++"};
+    let key = |flag : &str| (compiler_version.clone(), language.to_string(), flag.to_string());
+
+    let mut cache = read_flag_cache();
+    let unprobed_flags = flags.iter()
+                              .copied()
+                              .filter(|flag| !cache.contains_key(&key(flag)))
+                              .collect::<Vec<&str>>();
+
+    if !unprobed_flags.is_empty() {
+        let results = if flags_supported(compiler, is_c_source_file, &unprobed_flags) {
+            unprobed_flags.iter().map(|flag| (*flag, true)).collect::<Vec<_>>()
+        }
+        else {
+            unprobed_flags.par_iter()
+                          .map(|flag| (*flag, flags_supported(compiler, is_c_source_file, &[flag])))
+                          .collect::<Vec<_>>()
+        };
+        cache.extend(results.into_iter().map(|(flag, supported)| (key(flag), supported)));
+        write_flag_cache(&cache);
+    }
+
+    flags.iter()
+         .copied()
+         .filter(|flag| cache.get(&key(flag)) == Some(&false))
+         .collect()
+}
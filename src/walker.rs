@@ -1,38 +1,136 @@
-use std::{fs::{self}, path::Path, collections::HashMap};
+use std::{fs::{self}, path::{Path, PathBuf}, collections::{HashMap, HashSet}};
 
 use color_print::cformat;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use walkdir::WalkDir;
 
-use crate::{compiler::{self, to_output_file}, buildtable::{BuildTable, BUILD_TABLE_OBJECT_FILE_DIRECTORY}};
+use crate::{compiler::{self, to_output_file}, buildtable::{BuildTable, BuildDirectories, FileRecord, command_fingerprint}, build::{Build, Sources}, logger::error};
 
-const SOURCE_EXTENSIONS : [&str;4] = ["c", "cpp", "cc", "cxx"];
+/// Decides which of the files in the source directories are compiled, using
+/// the 'globs' and 'exclude' patterns of the '[sources]' section. The patterns
+/// are matched against the paths relative to the project's root directory.
+struct SourceFilter
+{
+    globs : Option<GlobSet>,
+    exclude : GlobSet
+}
 
-/// Returns true if a source dependency that existed
-/// from last compilation is not found in 'dir'. This
-/// is for checking if a source file was deleted. If it
-/// was, then the entire project must be recompiled.
-#[inline]
-fn source_dependency_missing(dir : &str, build_table : &mut BuildTable) -> bool
+/// Compiles the glob patterns into a set, matched against paths relative to
+/// the project's root directory.
+pub fn try_build_glob_set(patterns : &[String]) -> Result<GlobSet, String>
 {
-    let paths = fs::read_dir(BUILD_TABLE_OBJECT_FILE_DIRECTORY)
-                            .expect("Failed to read from build table object file directory");
-
-    let mut source_file_path = String::new();
-    for path in paths {
-        let path = path.unwrap().path();
-        let mut exists = false;
-        let object_file_path = &path.to_str().unwrap().to_string();
-
-        for ext in SOURCE_EXTENSIONS {
-            source_file_path = to_output_file(&path, dir, ext);
-            if Path::new(&source_file_path).exists() {
-                exists = true;
-                break;
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        // '*' should not match across directories, '**' does that
+        let glob = GlobBuilder::new(pattern).literal_separator(true)
+                                            .build()
+                                            .map_err(|e| format!("invalid glob pattern '{pattern}': {e}"))?;
+        builder.add(glob);
+    }
+    builder.build().map_err(|e| e.to_string())
+}
+
+/// Compiles the glob patterns into a set. Terminates the program if one of
+/// the patterns is invalid.
+fn build_glob_set(patterns : &[String]) -> GlobSet
+{
+    try_build_glob_set(patterns).unwrap_or_else(|e| {
+        error(&format!("{e} in 'Build.toml'"));
+        std::process::exit(1);
+    })
+}
+
+impl SourceFilter
+{
+    fn new(sources : &Sources) -> SourceFilter
+    {
+        SourceFilter {
+            globs : sources.globs.as_deref().map(build_glob_set),
+            exclude : build_glob_set(sources.exclude.as_deref().unwrap_or_default())
+        }
+    }
+
+    /// Returns true if the file (or directory) matches one of the exclusion patterns.
+    fn is_excluded(&self, path : &Path) -> bool
+    {
+        self.exclude.is_match(compiler::without_current_dir(path))
+    }
+
+    /// Returns true if the file is a C/C++ source file that should be compiled.
+    fn is_source_file(&self, path : &Path) -> bool
+    {
+        let path_str = path.to_str().unwrap();
+        if !compiler::is_cpp_source_file(path_str) && !compiler::is_c_source_file(path_str) {
+            return false;
+        }
+
+        let relative_path = compiler::without_current_dir(path);
+        !self.exclude.is_match(&relative_path) && 
+        self.globs.iter().all(|globs| globs.is_match(&relative_path))
+    }
+}
+
+/// Returns every source file in the source directories (and their subdirectories)
+/// that should be compiled. A file is only listed once, even if the source
+/// directories overlap (like 'src' and 'src/net').
+fn source_files_in_dirs(build_config : &Build, filter : &SourceFilter) -> Vec<PathBuf>
+{
+    let mut source_files = Vec::new();
+    let mut seen_files = HashSet::new();
+    for dir in build_config.source_dirs() {
+        // excluded directories are skipped entirely
+        let entries = WalkDir::new(dir).into_iter()
+                                       .filter_entry(|entry| !filter.is_excluded(entry.path()))
+                                       .flatten();
+        for entry in entries {
+            if entry.file_type().is_file() && filter.is_source_file(entry.path())
+               && seen_files.insert(compiler::without_current_dir(entry.path())) {
+                source_files.push(entry.into_path());
             }
         }
+    }
+    source_files
+}
+
+/// Returns every source file of the project that should be compiled.
+pub fn find_source_files(build_config : &Build) -> Vec<PathBuf>
+{
+    source_files_in_dirs(build_config, &SourceFilter::new(&build_config.sources))
+}
+
+/// Returns the source file `object_file` was compiled from. Object files
+/// mirror the layout of the project, so this is the object file's path
+/// relative to the object file directory without the '.o' extension.
+fn object_source_file(object_file : &Path, object_file_directory : &str) -> Option<String>
+{
+    let relative_path = object_file.strip_prefix(object_file_directory).ok()?;
+    let source_file = relative_path.to_str()?.strip_suffix(".o")?;
+    Some(format!("./{source_file}"))
+}
+
+/// Returns true if a source dependency that existed
+/// from last compilation is not found anymore (or is
+/// no longer part of the build). This is for checking
+/// if a source file was deleted. If it was, then the
+/// entire project must be recompiled.
+#[inline]
+fn source_dependency_missing(build_table : &mut BuildTable, dirs : &BuildDirectories, filter : &SourceFilter) -> bool
+{
+    for path in WalkDir::new(&dirs.obj).into_iter().flatten() {
+        let object_file_path = path.path();
+        // some builds may store other files in the 'obj' directory
+        let source_file_path = match object_source_file(object_file_path, &dirs.obj) {
+            Some(source_file_path) if path.file_type().is_file() => source_file_path,
+            _ => continue
+        };
 
-        if !exists {
-            // Remove object file since it is no longer in the source directory 
+        if !Path::new(&source_file_path).exists() || !filter.is_source_file(Path::new(&source_file_path)) {
+            // Remove object file (and its dependency file) since it is no longer in the source directory 
             fs::remove_file(object_file_path).expect("Failed to remove object file");
+            let dep_file = to_output_file(Path::new(&source_file_path), &dirs.deps, "d");
+            if Path::new(&dep_file).exists() {
+                fs::remove_file(dep_file).expect("Failed to remove dependency file");
+            }
             build_table.erase(&source_file_path);
             return true;
         }
@@ -43,28 +141,29 @@ fn source_dependency_missing(dir : &str, build_table : &mut BuildTable) -> bool
 }
 
 /// Retrieves the source files that need to be compiled
-pub fn retrieve_source_files(dir: &str, 
+pub fn retrieve_source_files(build_config : &Build,
                              build_table : &mut BuildTable,
-                             old_table : &HashMap<String, u64>) -> Vec<String>
+                             old_table : &HashMap<String, FileRecord>) -> Vec<String>
 {
     let mut source_files = Vec::new();
-    let mut has_source_file = false;
-    let paths = fs::read_dir(dir).expect("Failed to read from directory");
-    let source_dependency_missing = source_dependency_missing(dir, build_table);
+    let filter = SourceFilter::new(&build_config.sources);
+    let source_dependency_missing = source_dependency_missing(build_table, &build_config.dirs, &filter);
     let mut source_file_needs_to_be_recompiled = false;
+    let all_source_files = source_files_in_dirs(build_config, &filter);
     
     // only append the c/c++ files that need to be recompiled into the vector
-    for path in paths.flatten() {
-        let retrieved_path = path.path();
+    for retrieved_path in &all_source_files {
         let path_str = retrieved_path.to_str().unwrap();
-        if compiler::is_cpp_source_file(path_str) || compiler::is_c_source_file(path_str) {
-            has_source_file = true;
+        let command = command_fingerprint(&compiler::object_file_command(build_config, path_str));
 
-            if source_dependency_missing ||
-               build_table.needs_to_be_recompiled(&retrieved_path, old_table) {
-                source_file_needs_to_be_recompiled = true;
-                source_files.push(path_str.to_string());
-            }
+        // every file is recompiled, so their records have to be brought up to date
+        // here, otherwise the next build compares them to the outdated ones again
+        if source_dependency_missing {
+            build_table.refresh_record(retrieved_path, command, old_table);
+        }
+        if source_dependency_missing || build_table.needs_to_be_recompiled(retrieved_path, command, old_table) {
+            source_file_needs_to_be_recompiled = true;
+            source_files.push(path_str.to_string());
         }
     }
 
@@ -74,11 +173,11 @@ pub fn retrieve_source_files(dir: &str,
 
     // If no source files were found, print an error and terminate the program as there is nothing
     // to do
-    if !has_source_file {
-        eprintln!("{}", cformat!("<bold><red>error</red></bold>: no source files found in '{}'. Terminating program.", dir));
+    if all_source_files.is_empty() {
+        eprintln!("{}", cformat!("<bold><red>error</red></bold>: no source files found in '{}'. Terminating program.", 
+                                build_config.source_dirs().join("', '")));
         std::process::exit(1);
     }
 
     source_files
 }
-